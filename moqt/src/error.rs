@@ -1,4 +1,4 @@
-use crate::message::message_parser::ErrorCode;
+use crate::message::message_parser::{ErrorCode, SetupViolation};
 use std::string::FromUtf8Error;
 use thiserror::Error;
 
@@ -39,6 +39,8 @@ pub enum Error {
     ErrNonEmptyPayloadMustBeWithNormalObjectStatus,
     #[error("parse error with code: {0} and reason: {1}")]
     ErrParseError(ErrorCode, String),
+    #[error("setup violation: {0}")]
+    ErrSetupViolation(SetupViolation),
     #[error("frame error with reason: {0}")]
     ErrFrameError(String),
     #[error("stream error with code: {0} and reason: {1}")]
@@ -53,3 +55,49 @@ pub enum Error {
     #[error("invalid string")]
     ErrInvalidString(#[from] FromUtf8Error),
 }
+
+impl Error {
+    /// The MoQT error code carried by this error. Errors without one, such as
+    /// malformed varints or short buffers, map to ProtocolViolation.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Error::ErrParseError(code, _) | Error::ErrStreamError(code, _) => *code,
+            _ => ErrorCode::ProtocolViolation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_code() {
+        for code in [
+            ErrorCode::NoError,
+            ErrorCode::InternalError,
+            ErrorCode::Unauthorized,
+            ErrorCode::ProtocolViolation,
+            ErrorCode::DuplicateTrackAlias,
+            ErrorCode::ParameterLengthMismatch,
+            ErrorCode::GoawayTimeout,
+        ] {
+            assert_eq!(
+                Error::ErrParseError(code, "reason".to_string()).error_code(),
+                code
+            );
+            assert_eq!(
+                Error::ErrStreamError(code, "reason".to_string()).error_code(),
+                code
+            );
+        }
+        assert_eq!(
+            Error::ErrMalformedVarInt.error_code(),
+            ErrorCode::ProtocolViolation
+        );
+        assert_eq!(
+            Error::ErrSetupViolation(SetupViolation::MissingPathOverQuic).error_code(),
+            ErrorCode::ProtocolViolation
+        );
+    }
+}
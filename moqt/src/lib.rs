@@ -9,7 +9,7 @@ mod serde;
 mod session;
 
 pub use error::{Error, Result};
-pub use serde::{parameters::Parameters, varint::VarInt, Deserializer, Serializer};
+pub use serde::{parameters::Parameters, varint::VarInt, Deserializer, SerializedSize, Serializer};
 
 /// match between client and server perspective, since there may be a proxy
 /// between them.
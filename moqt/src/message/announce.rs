@@ -1,20 +1,48 @@
 use crate::message::message_parser::ErrorCode;
-use crate::serde::parameters::ParameterKey;
-use crate::{Deserializer, Error, Parameters, Result, Serializer};
+use crate::message::{deserialize_track_namespace, MessageType, MAX_PARAMETERS};
+use crate::serde::parameters::{check_parameter, ParameterKey};
+use crate::{Deserializer, Error, Parameters, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct Announce {
     pub track_namespace: String,
     pub authorization_info: Option<String>,
 }
 
+impl Announce {
+    fn parameters(&self) -> Result<Option<Parameters>> {
+        let authorization_info = if let Some(authorization_info) = self.authorization_info.as_ref()
+        {
+            authorization_info
+        } else {
+            return Ok(None);
+        };
+        let mut parameters = Parameters::new();
+        parameters.insert(
+            ParameterKey::AuthorizationInfo,
+            authorization_info.to_string(),
+        )?;
+        Ok(Some(parameters))
+    }
+}
+
 impl Deserializer for Announce {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
-        let (track_namespace, tnsl) = String::deserialize(r)?;
+        let (track_namespace, tnsl) = deserialize_track_namespace(r)?;
 
         let mut authorization_info: Option<String> = None;
-        let (num_params, mut pl) = u64::deserialize(r)?;
+        let (num_params, mut pl) = usize::deserialize(r)?;
+        if num_params > MAX_PARAMETERS {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "Too many parameters in ANNOUNCE".to_string(),
+            ));
+        }
         // Parse parameters
         for _ in 0..num_params {
             let (key, kl) = u64::deserialize(r)?;
@@ -26,6 +54,13 @@ impl Deserializer for Announce {
                 return Err(Error::ErrBufferTooShort);
             }
 
+            if !check_parameter(MessageType::Announce, key)? {
+                // Skip unknown parameters.
+                r.advance(size);
+                pl += size;
+                continue;
+            }
+
             if key == ParameterKey::AuthorizationInfo as u64 {
                 if authorization_info.is_some() {
                     return Err(Error::ErrParseError(
@@ -54,16 +89,19 @@ impl Deserializer for Announce {
 impl Serializer for Announce {
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
         let mut l = self.track_namespace.serialize(w)?;
-
-        if let Some(authorization_info) = self.authorization_info.as_ref() {
-            let mut parameters = Parameters::new();
-            parameters.insert(
-                ParameterKey::AuthorizationInfo,
-                authorization_info.to_string(),
-            )?;
+        if let Some(parameters) = self.parameters()? {
             l += parameters.serialize(w)?;
         }
+        Ok(l)
+    }
+}
 
+impl SerializedSize for Announce {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.track_namespace.serialized_size()?;
+        if let Some(parameters) = self.parameters()? {
+            l += parameters.serialized_size()?;
+        }
         Ok(l)
     }
 }
@@ -98,4 +136,56 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_announce_empty_namespace() -> Result<()> {
+        let packet: Vec<u8> = vec![
+            0x06, 0x00, // track_namespace = ""
+            0x00, // no parameters
+        ];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(packet.as_ref());
+        assert!(matches!(
+            ControlMessage::deserialize(&mut cursor),
+            Err(Error::ErrParseError(ErrorCode::ProtocolViolation, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_announce_role_parameter_not_allowed() -> Result<()> {
+        let packet: Vec<u8> = vec![
+            0x06, 0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+            0x01, // 1 parameter
+            0x00, 0x01, 0x03, // role = PubSub
+        ];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(packet.as_ref());
+        if let Err(Error::ErrParseError(code, reason)) = ControlMessage::deserialize(&mut cursor) {
+            assert_eq!(code, ErrorCode::ProtocolViolation);
+            assert_eq!(reason, "ROLE parameter is not allowed in ANNOUNCE");
+        } else {
+            return Err(Error::ErrInvalidMessageType(0x06));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_announce_unknown_parameter_skipped() -> Result<()> {
+        let packet: Vec<u8> = vec![
+            0x06, 0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+            0x02, // 2 parameters
+            0x1f, 0x02, 0x01, 0x02, // unknown parameter
+            0x02, 0x03, 0x62, 0x61, 0x72, // authorization_info = "bar"
+        ];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(packet.as_ref());
+        let (message, l) = ControlMessage::deserialize(&mut cursor)?;
+        assert_eq!(
+            message,
+            ControlMessage::Announce(Announce {
+                track_namespace: "foo".to_string(),
+                authorization_info: Some("bar".to_string()),
+            })
+        );
+        assert_eq!(l, packet.len());
+        Ok(())
+    }
 }
@@ -1,6 +1,11 @@
-use crate::{Deserializer, Result, Serializer};
+use crate::message::deserialize_track_namespace;
+use crate::{Deserializer, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum AnnounceErrorCode {
     #[default]
@@ -8,12 +13,20 @@ pub enum AnnounceErrorCode {
     AnnounceNotSupported = 1,
 }
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct AnnounceErrorReason {
     pub error_code: AnnounceErrorCode,
     pub reason_phrase: String,
 }
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct AnnounceError {
     pub track_namespace: String,
@@ -23,7 +36,7 @@ pub struct AnnounceError {
 
 impl Deserializer for AnnounceError {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
-        let (track_namespace, tnsl) = String::deserialize(r)?;
+        let (track_namespace, tnsl) = deserialize_track_namespace(r)?;
         let (error_code, ecl) = u64::deserialize(r)?;
         let (reason_phrase, rpl) = String::deserialize(r)?;
         Ok((
@@ -46,6 +59,15 @@ impl Serializer for AnnounceError {
     }
 }
 
+impl SerializedSize for AnnounceError {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.track_namespace.serialized_size()?;
+        l += self.error_code.serialized_size()?;
+        l += self.reason_phrase.serialized_size()?;
+        Ok(l)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -77,4 +99,30 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_announce_error_empty_reason() -> Result<()> {
+        let expected_packet: Vec<u8> = vec![
+            0x08, 0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+            0x01, // error_code = 1
+            0x00, // reason_phrase = ""
+        ];
+
+        let expected_message = ControlMessage::AnnounceError(AnnounceError {
+            track_namespace: "foo".to_string(),
+            error_code: 1,
+            reason_phrase: String::new(),
+        });
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(expected_packet.as_ref());
+        let (actual_message, actual_len) = ControlMessage::deserialize(&mut cursor)?;
+        assert_eq!(expected_message, actual_message);
+        assert_eq!(expected_packet.len(), actual_len);
+
+        let mut actual_packet = vec![];
+        let _ = expected_message.serialize(&mut actual_packet)?;
+        assert_eq!(expected_packet, actual_packet);
+
+        Ok(())
+    }
 }
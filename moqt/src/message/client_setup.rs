@@ -1,9 +1,13 @@
 use crate::message::message_parser::ErrorCode;
-use crate::message::{Role, Version};
-use crate::serde::parameters::ParameterKey;
-use crate::{Deserializer, Error, Parameters, Result, Serializer};
+use crate::message::{MessageType, Role, Version, MAX_PARAMETERS, MAX_SUPPORTED_VERSIONS};
+use crate::serde::parameters::{check_parameter, deserialize_int_parameter, ParameterKey};
+use crate::{Deserializer, Error, Parameters, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct ClientSetup {
     pub supported_versions: Vec<Version>,
@@ -19,11 +23,62 @@ impl ClientSetup {
             ..Default::default()
         }
     }
+
+    /// CLIENT_SETUP over WebTransport, where the path comes from the URL and
+    /// the PATH parameter is not sent.
+    pub fn for_webtransport(supported_versions: Vec<Version>, role: Role) -> Self {
+        Self {
+            supported_versions,
+            role: Some(role),
+            path: None,
+            uses_web_transport: true,
+        }
+    }
+
+    /// CLIENT_SETUP over raw QUIC, which must carry the PATH parameter.
+    pub fn for_quic(supported_versions: Vec<Version>, role: Role, path: String) -> Self {
+        Self {
+            supported_versions,
+            role: Some(role),
+            path: Some(path),
+            uses_web_transport: false,
+        }
+    }
+    // The peer would reject the message, so fail before writing anything.
+    fn check_version_count(&self) -> Result<()> {
+        if self.supported_versions.len() > MAX_SUPPORTED_VERSIONS {
+            return Err(Error::ErrFrameError(format!(
+                "CLIENT_SETUP offers {} versions, more than the {} a peer accepts; offer fewer versions",
+                self.supported_versions.len(),
+                MAX_SUPPORTED_VERSIONS
+            )));
+        }
+        Ok(())
+    }
+
+    fn parameters(&self) -> Result<Parameters> {
+        let mut parameters = Parameters::new();
+        if let Some(role) = self.role.as_ref() {
+            parameters.insert(ParameterKey::Role, *role)?;
+        }
+        if !self.uses_web_transport {
+            if let Some(path) = self.path.as_ref() {
+                parameters.insert(ParameterKey::Path, path.to_string())?;
+            }
+        }
+        Ok(parameters)
+    }
 }
 
 impl Deserializer for ClientSetup {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
         let (number_supported_versions, mut tl) = usize::deserialize(r)?;
+        if number_supported_versions > MAX_SUPPORTED_VERSIONS {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "Too many supported versions in CLIENT_SETUP".to_string(),
+            ));
+        }
         let mut supported_versions = Vec::with_capacity(number_supported_versions);
         for _ in 0..number_supported_versions {
             let (version, vl) = Version::deserialize(r)?;
@@ -31,7 +86,13 @@ impl Deserializer for ClientSetup {
             tl += vl;
         }
 
-        let (num_params, npl) = u64::deserialize(r)?;
+        let (num_params, npl) = usize::deserialize(r)?;
+        if num_params > MAX_PARAMETERS {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "Too many parameters in CLIENT_SETUP".to_string(),
+            ));
+        }
         tl += npl;
 
         let mut role: Option<Role> = None;
@@ -48,6 +109,13 @@ impl Deserializer for ClientSetup {
                 return Err(Error::ErrBufferTooShort);
             }
 
+            if !check_parameter(MessageType::ClientSetup, key)? {
+                // Skip unknown parameters.
+                r.advance(size);
+                tl += size;
+                continue;
+            }
+
             if key == ParameterKey::Role as u64 {
                 if role.is_some() {
                     return Err(Error::ErrParseError(
@@ -55,20 +123,13 @@ impl Deserializer for ClientSetup {
                         "ROLE parameter appears twice in SETUP".to_string(),
                     ));
                 }
-                let (r, rl) = u64::deserialize(r)?;
+                let (r, rl) = deserialize_int_parameter(r, size, ParameterKey::Role)?;
                 tl += rl;
 
-                if rl != size {
-                    return Err(Error::ErrParseError(
-                        ErrorCode::ParameterLengthMismatch,
-                        "Parameter length does not match varint encoding".to_string(),
-                    ));
-                }
-
                 role = Some(r.try_into().map_err(|_| {
                     Error::ErrParseError(
                         ErrorCode::ProtocolViolation,
-                        "Invalid ROLE parameter".to_string(),
+                        format!("ROLE parameter value {} is out of range", r),
                     )
                 })?);
             } else if key == ParameterKey::Path as u64 {
@@ -107,22 +168,24 @@ impl Deserializer for ClientSetup {
 
 impl Serializer for ClientSetup {
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
+        self.check_version_count()?;
         let mut l = self.supported_versions.len().serialize(w)?;
         for supported_version in self.supported_versions.iter() {
             l += supported_version.serialize(w)?;
         }
+        l += self.parameters()?.serialize(w)?;
+        Ok(l)
+    }
+}
 
-        let mut parameters = Parameters::new();
-        if let Some(role) = self.role.as_ref() {
-            parameters.insert(ParameterKey::Role, *role)?;
-        }
-        if !self.uses_web_transport {
-            if let Some(path) = self.path.as_ref() {
-                parameters.insert(ParameterKey::Path, path.to_string())?;
-            }
+impl SerializedSize for ClientSetup {
+    fn serialized_size(&self) -> Result<usize> {
+        self.check_version_count()?;
+        let mut l = self.supported_versions.len().serialized_size()?;
+        for supported_version in self.supported_versions.iter() {
+            l += supported_version.serialized_size()?;
         }
-        l += parameters.serialize(w)?;
-
+        l += self.parameters()?.serialized_size()?;
         Ok(l)
     }
 }
@@ -182,4 +245,51 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_client_setup_constructors() -> Result<()> {
+        let client_setup = ClientSetup::for_webtransport(vec![Version::Draft04], Role::PubSub);
+        assert_eq!(client_setup.path, None);
+        assert!(client_setup.uses_web_transport);
+
+        let mut actual_packet = vec![];
+        let _ = ControlMessage::ClientSetup(client_setup).serialize(&mut actual_packet)?;
+        assert_eq!(
+            actual_packet,
+            vec![
+                0x40, 0x40, // type
+                0x01, 0xc0, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x04, // Draft04
+                0x01, // 1 parameter, no PATH
+                0x00, 0x01, 0x03, // role = PubSub
+            ]
+        );
+
+        let client_setup =
+            ClientSetup::for_quic(vec![Version::Draft04], Role::Subscriber, "foo".to_string());
+        assert_eq!(client_setup.path, Some("foo".to_string()));
+        assert_eq!(client_setup.role, Some(Role::Subscriber));
+        assert!(!client_setup.uses_web_transport);
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_setup_too_many_versions() -> Result<()> {
+        let client_setup =
+            ClientSetup::for_quic(vec![Version::Draft04; 500], Role::PubSub, "foo".to_string());
+        let mut packet = vec![];
+        if let Err(Error::ErrFrameError(reason)) = client_setup.serialize(&mut packet) {
+            assert!(reason.contains("offers 500 versions"));
+        } else {
+            return Err(Error::ErrFrameError("expected an error".to_string()));
+        }
+        assert!(packet.is_empty());
+
+        let client_setup = ClientSetup::for_quic(
+            vec![Version::Draft04; MAX_SUPPORTED_VERSIONS],
+            Role::PubSub,
+            "foo".to_string(),
+        );
+        client_setup.serialize(&mut packet)?;
+        Ok(())
+    }
 }
@@ -1,11 +1,37 @@
-use crate::{Deserializer, Result, Serializer};
+use crate::{Deserializer, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct GoAway {
     pub new_session_uri: String,
 }
 
+impl GoAway {
+    /// Returns true if new_session_uri is empty, meaning the current URI is
+    /// reused, or looks like an absolute URI: a scheme followed by ':' and no
+    /// control characters. This is not a full RFC 3986 check.
+    pub fn has_plausible_uri(&self) -> bool {
+        let uri = &self.new_session_uri;
+        if uri.is_empty() {
+            return true;
+        }
+        if uri.chars().any(char::is_control) {
+            return false;
+        }
+        let scheme = match uri.split_once(':') {
+            Some((scheme, _)) => scheme,
+            None => return false,
+        };
+        let mut chars = scheme.chars();
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    }
+}
+
 impl Deserializer for GoAway {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
         let (new_session_uri, nsul) = String::deserialize(r)?;
@@ -19,6 +45,12 @@ impl Serializer for GoAway {
     }
 }
 
+impl SerializedSize for GoAway {
+    fn serialized_size(&self) -> Result<usize> {
+        self.new_session_uri.serialized_size()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -44,4 +76,22 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_go_away_plausible_uri() {
+        for (uri, plausible) in [
+            ("", true),
+            ("https://example.com/moq", true),
+            ("moqt+quic:relay", true),
+            ("example.com/moq", false),
+            ("://example.com", false),
+            ("1https://example.com", false),
+            ("https://example.com/\r\n\x00", false),
+        ] {
+            let go_away = GoAway {
+                new_session_uri: uri.to_string(),
+            };
+            assert_eq!(go_away.has_plausible_uri(), plausible, "{:?}", uri);
+        }
+    }
 }
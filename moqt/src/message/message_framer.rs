@@ -1,19 +1,124 @@
 use crate::message::object::{ObjectForwardingPreference, ObjectHeader, ObjectStatus};
-use crate::message::{ControlMessage, MessageType};
-use crate::{Error, Result, Serializer};
-use bytes::{BufMut, Bytes};
+use crate::message::{ControlMessage, MessageType, Perspective, MAX_MESSSAGE_HEADER_SIZE};
+use crate::{Error, Result, SerializedSize, Serializer};
+use bytes::{BufMut, Bytes, BytesMut};
 
-pub struct MessageFramer;
+/// Serialize structured message data into a wire image. The perspective
+/// determines which setup message may be sent.
+pub struct MessageFramer {
+    perspective: Perspective,
+    // If set, datagrams that would not fit are refused.
+    max_datagram_size: Option<usize>,
+}
 
 impl MessageFramer {
+    pub fn new(perspective: Perspective) -> Self {
+        Self {
+            perspective,
+            max_datagram_size: None,
+        }
+    }
+
+    pub fn perspective(&self) -> Perspective {
+        self.perspective
+    }
+
+    pub fn max_datagram_size(&self) -> Option<usize> {
+        self.max_datagram_size
+    }
+
+    pub fn set_max_datagram_size(&mut self, max_datagram_size: Option<usize>) {
+        self.max_datagram_size = max_datagram_size;
+    }
+
     pub fn serialize_control_message<W: BufMut>(
+        &self,
         control_message: ControlMessage,
         w: &mut W,
     ) -> Result<usize> {
+        self.serialize_control_into(w, &control_message)
+    }
+
+    /// Writes a control message straight into the caller's buffer, e.g. a
+    /// transport send buffer, without taking ownership of the message.
+    /// Nothing is written unless the whole message can be: on any error,
+    /// including |w| having too little room, |w| is left untouched.
+    pub fn serialize_control_into<W: BufMut>(
+        &self,
+        w: &mut W,
+        control_message: &ControlMessage,
+    ) -> Result<usize> {
+        let size = self.control_message_size(control_message)?;
+        if w.remaining_mut() < size {
+            return Err(Error::ErrBufferTooShort);
+        }
         control_message.serialize(w)
     }
 
+    /// Like serialize_control_message, but appends to |buf|, reserving the
+    /// message's size up front so |buf| grows at most once per message.
+    pub fn serialize_control_to_bytes(
+        &self,
+        control_message: ControlMessage,
+        buf: &mut BytesMut,
+    ) -> Result<usize> {
+        let size = self.control_message_size(&control_message)?;
+        buf.reserve(size);
+        control_message.serialize(buf)
+    }
+
+    fn check_perspective(&self, control_message: &ControlMessage) -> Result<()> {
+        match control_message {
+            ControlMessage::ClientSetup(_) if self.perspective == Perspective::Server => Err(
+                Error::ErrFrameError("Server cannot send CLIENT_SETUP".to_string()),
+            ),
+            ControlMessage::ServerSetup(_) if self.perspective == Perspective::Client => Err(
+                Error::ErrFrameError("Client cannot send SERVER_SETUP".to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    // The peer's parser refuses to buffer more than MAX_MESSSAGE_HEADER_SIZE,
+    // so fail locally instead.
+    fn check_size(size: usize) -> Result<usize> {
+        if size > MAX_MESSSAGE_HEADER_SIZE {
+            return Err(Error::ErrFrameError(format!(
+                "Control message exceeds {} bytes",
+                MAX_MESSSAGE_HEADER_SIZE
+            )));
+        }
+        Ok(size)
+    }
+
+    /// Writes an explicit list of (key, value) parameters with the count and
+    /// length prefixes used in every message, for tools and tests that need
+    /// parameters the typed messages cannot express. Like Parameters, they
+    /// are written in ascending key order; repeated keys keep their order.
+    pub fn serialize_raw_parameters(&self, parameters: &[(u64, Bytes)]) -> Result<BytesMut> {
+        let mut sorted: Vec<&(u64, Bytes)> = parameters.iter().collect();
+        sorted.sort_by_key(|(key, _)| *key);
+        let mut buf = BytesMut::new();
+        parameters.len().serialize(&mut buf)?;
+        for (key, value) in sorted {
+            key.serialize(&mut buf)?;
+            value.len().serialize(&mut buf)?;
+            value.serialize(&mut buf)?;
+        }
+        Ok(buf)
+    }
+
+    /// Returns the number of bytes serialize_control_message would write,
+    /// without serializing the message. Fails the same way serializing it
+    /// would, e.g. for a message this perspective cannot send or one too
+    /// large for the peer.
+    pub fn control_message_size(&self, control_message: &ControlMessage) -> Result<usize> {
+        self.check_perspective(control_message)?;
+        MessageFramer::check_size(control_message.serialized_size()?)
+    }
+
     pub fn serialize_object_header<W: BufMut>(
+        &self,
         object_header: ObjectHeader,
         is_first_in_stream: bool,
         w: &mut W,
@@ -50,7 +155,7 @@ impl MessageFramer {
                     tl += object_header.object_id.serialize(w)?;
                     tl += object_payload_length.serialize(w)?;
                     if object_payload_length == 0 {
-                        tl += (object_header.object_status as u64).serialize(w)?
+                        tl += object_header.object_status.serialize(w)?
                     }
                     return Ok(tl);
                 }
@@ -65,7 +170,7 @@ impl MessageFramer {
                     tl += object_header.object_id.serialize(w)?;
                     tl += object_payload_length.serialize(w)?;
                     if object_payload_length == 0 {
-                        tl += (object_header.object_status as u64).serialize(w)?
+                        tl += object_header.object_status.serialize(w)?
                     }
                     return Ok(tl);
                 }
@@ -98,7 +203,7 @@ impl MessageFramer {
                 tl += object_header.object_id.serialize(w)?;
                 tl += object_payload_length.serialize(w)?;
                 if object_payload_length == 0 {
-                    tl += (object_header.object_status as u64).serialize(w)?;
+                    tl += object_header.object_status.serialize(w)?;
                 }
                 Ok(tl)
             }
@@ -118,7 +223,7 @@ impl MessageFramer {
                 tl += object_header.object_id.serialize(w)?;
                 tl += object_payload_length.serialize(w)?;
                 if object_payload_length == 0 {
-                    tl += (object_header.object_status as u64).serialize(w)?;
+                    tl += object_header.object_status.serialize(w)?;
                 }
                 Ok(tl)
             }
@@ -129,7 +234,7 @@ impl MessageFramer {
                 tl += object_header.group_id.serialize(w)?;
                 tl += object_header.object_id.serialize(w)?;
                 tl += object_header.object_send_order.serialize(w)?;
-                tl += (object_header.object_status as u64).serialize(w)?;
+                tl += object_header.object_status.serialize(w)?;
 
                 Ok(tl)
             }
@@ -137,6 +242,7 @@ impl MessageFramer {
     }
 
     pub(crate) fn serialize_object<W: BufMut>(
+        &self,
         object_header: ObjectHeader,
         is_first_in_stream: bool,
         payload: Bytes,
@@ -144,13 +250,53 @@ impl MessageFramer {
     ) -> Result<usize> {
         let mut adjusted_object_header = object_header;
         adjusted_object_header.object_payload_length = Some(payload.len() as u64);
-        let mut tl =
-            MessageFramer::serialize_object_header(adjusted_object_header, is_first_in_stream, w)?;
+        let mut tl = self.serialize_object_header(adjusted_object_header, is_first_in_stream, w)?;
         tl += payload.serialize(w)?;
         Ok(tl)
     }
 
+    /// Serializes a whole group stream: the first object is written along with
+    /// the stream header, every following object as a follow-on object. All
+    /// objects must belong to the same subscription, track and group.
+    pub fn serialize_group_stream<W: BufMut>(
+        &self,
+        objects: &[(ObjectHeader, Bytes)],
+        w: &mut W,
+    ) -> Result<usize> {
+        let first = if let Some((first, _)) = objects.first() {
+            first
+        } else {
+            return Err(Error::ErrInvalidObjectType(
+                "Group stream must contain at least one object".to_string(),
+            ));
+        };
+        if first.object_forwarding_preference != ObjectForwardingPreference::Group {
+            return Err(Error::ErrInvalidObjectType(
+                "Group stream requires Group forwarding preference".to_string(),
+            ));
+        }
+        for (object_header, _) in objects.iter() {
+            if object_header.object_forwarding_preference != first.object_forwarding_preference
+                || object_header.subscribe_id != first.subscribe_id
+                || object_header.track_alias != first.track_alias
+                || object_header.group_id != first.group_id
+            {
+                return Err(Error::ErrInvalidObjectType(
+                    "All objects in a group stream must share subscribe_id, track_alias and group_id"
+                        .to_string(),
+                ));
+            }
+        }
+
+        let mut tl = 0;
+        for (i, (object_header, payload)) in objects.iter().enumerate() {
+            tl += self.serialize_object(*object_header, i == 0, payload.clone(), w)?;
+        }
+        Ok(tl)
+    }
+
     pub fn serialize_object_datagram<W: BufMut>(
+        &self,
         object_header: ObjectHeader,
         payload: Bytes,
         w: &mut W,
@@ -161,6 +307,30 @@ impl MessageFramer {
             ));
         }
 
+        let mut tl = if let Some(max_datagram_size) = self.max_datagram_size {
+            // Stage the header so nothing is written if the datagram is too big.
+            let mut header = BytesMut::new();
+            MessageFramer::serialize_datagram_header(&object_header, &mut header)?;
+            let datagram_size = header.len() + payload.len();
+            if datagram_size > max_datagram_size {
+                return Err(Error::ErrFrameError(format!(
+                    "OBJECT_DATAGRAM of {} bytes exceeds max datagram size {}",
+                    datagram_size, max_datagram_size
+                )));
+            }
+            header.freeze().serialize(w)?
+        } else {
+            MessageFramer::serialize_datagram_header(&object_header, w)?
+        };
+        tl += payload.serialize(w)?;
+
+        Ok(tl)
+    }
+
+    fn serialize_datagram_header<W: BufMut>(
+        object_header: &ObjectHeader,
+        w: &mut W,
+    ) -> Result<usize> {
         let mut tl = 0;
         tl += MessageType::ObjectDatagram.serialize(w)?;
         tl += object_header.subscribe_id.serialize(w)?;
@@ -168,9 +338,7 @@ impl MessageFramer {
         tl += object_header.group_id.serialize(w)?;
         tl += object_header.object_id.serialize(w)?;
         tl += object_header.object_send_order.serialize(w)?;
-        tl += (object_header.object_status as u64).serialize(w)?;
-        tl += payload.serialize(w)?;
-
+        tl += object_header.object_status.serialize(w)?;
         Ok(tl)
     }
 }
@@ -1,15 +1,21 @@
+use crate::message::client_setup::ClientSetup;
 use crate::message::message_framer::MessageFramer;
+use crate::message::message_parser::{MessageParser, MessageParserEvent};
 use crate::message::message_test::{
     create_test_message, MessageStructuredData, TestMessageBase, TestObjectDatagramMessage,
     TestStreamHeaderGroupMessage, TestStreamHeaderTrackMessage, TestStreamMiddlerGroupMessage,
     TestStreamMiddlerTrackMessage,
 };
 use crate::message::object::{ObjectForwardingPreference, ObjectHeader, ObjectStatus};
+use crate::message::server_setup::ServerSetup;
 use crate::message::subscribe::Subscribe;
 use crate::message::subscribe_update::SubscribeUpdate;
-use crate::message::{ControlMessage, FilterType, FullSequence, MessageType};
+use crate::message::{
+    ControlMessage, FilterType, FullSequence, MessageType, Perspective, Role, Version,
+    MAX_MESSSAGE_HEADER_SIZE,
+};
 use crate::{Error, Result};
-use bytes::{BufMut, Bytes};
+use bytes::{BufMut, Bytes, BytesMut};
 use rstest::rstest;
 
 struct TestFramerParams {
@@ -29,6 +35,7 @@ impl TestFramerParams {
 struct TestFramer {
     message_type: MessageType,
     uses_web_transport: bool,
+    framer: MessageFramer,
 }
 
 impl TestFramer {
@@ -36,6 +43,11 @@ impl TestFramer {
         Self {
             message_type: params.message_type,
             uses_web_transport: params.uses_web_transport,
+            framer: MessageFramer::new(if params.message_type == MessageType::ServerSetup {
+                Perspective::Server
+            } else {
+                Perspective::Client
+            }),
         }
     }
 
@@ -58,7 +70,8 @@ impl TestFramer {
                     } else {
                         return Err(Error::ErrInvalidMessageType(self.message_type as u64));
                     };
-                MessageFramer::serialize_object(object_header, true, Bytes::from_static(b"foo"), w)
+                self.framer
+                    .serialize_object(object_header, true, Bytes::from_static(b"foo"), w)
             }
             MessageType::ObjectDatagram => {
                 Err(Error::ErrInvalidMessageType(self.message_type as u64))
@@ -70,7 +83,7 @@ impl TestFramer {
                     } else {
                         return Err(Error::ErrInvalidMessageType(self.message_type as u64));
                     };
-                MessageFramer::serialize_control_message(control_message, w)
+                self.framer.serialize_control_message(control_message, w)
             }
         }
     }
@@ -112,19 +125,131 @@ fn test_framer_one_message(params: (MessageType, bool)) -> Result<()> {
     Ok(())
 }
 
+#[rstest(
+    params => [
+    (MessageType::ObjectStream, true), // ObjectDatagram is a unique set of tests.
+    (MessageType::StreamHeaderTrack, true),
+    (MessageType::StreamHeaderGroup, true),
+    (MessageType::Subscribe, true),
+    (MessageType::SubscribeOk, true),
+    (MessageType::SubscribeError, true),
+    (MessageType::UnSubscribe, true),
+    (MessageType::SubscribeDone, true),
+    (MessageType::SubscribeUpdate, true),
+    (MessageType::Announce, true),
+    (MessageType::AnnounceOk, true),
+    (MessageType::AnnounceError, true),
+    (MessageType::AnnounceCancel, true),
+    (MessageType::UnAnnounce, true),
+    (MessageType::TrackStatusRequest, true),
+    (MessageType::TrackStatus, true),
+    (MessageType::ClientSetup, true),
+    (MessageType::ClientSetup, false),
+    (MessageType::ServerSetup, true),
+    (MessageType::GoAway, true),
+    ]
+)]
+fn test_parse_then_reframe(params: (MessageType, bool)) -> Result<()> {
+    let tester = TestFramer::new(&TestFramerParams::new(params.0, params.1));
+    let message = tester.make_message();
+    let mut parser = MessageParser::new(params.1);
+    parser.process_data(&mut message.packet_sample(), true);
+    let structured_data = match parser.poll_event() {
+        Some(MessageParserEvent::ControlMessage(control_message)) => {
+            MessageStructuredData::Control(control_message)
+        }
+        Some(MessageParserEvent::ObjectMessage(object_header, payload, true)) => {
+            assert_eq!(&payload[..], b"foo");
+            MessageStructuredData::Object(object_header)
+        }
+        _ => return Err(Error::ErrInvalidMessageType(params.0 as u64)),
+    };
+    assert!(parser.poll_event().is_none());
+
+    let mut buffer = vec![];
+    let size = tester.serialize_message(structured_data, &mut buffer)?;
+    assert_eq!(size, buffer.len());
+    assert_eq!(&buffer[..], message.packet_sample());
+    Ok(())
+}
+
+#[rstest(
+    params => [
+    (MessageType::Subscribe, true),
+    (MessageType::SubscribeOk, true),
+    (MessageType::SubscribeError, true),
+    (MessageType::UnSubscribe, true),
+    (MessageType::SubscribeDone, true),
+    (MessageType::SubscribeUpdate, true),
+    (MessageType::Announce, true),
+    (MessageType::AnnounceOk, true),
+    (MessageType::AnnounceError, true),
+    (MessageType::AnnounceCancel, true),
+    (MessageType::UnAnnounce, true),
+    (MessageType::TrackStatusRequest, true),
+    (MessageType::TrackStatus, true),
+    (MessageType::ClientSetup, true),
+    (MessageType::ClientSetup, false),
+    (MessageType::ServerSetup, true),
+    (MessageType::GoAway, true),
+    ]
+)]
+fn test_control_message_size(params: (MessageType, bool)) -> Result<()> {
+    let tester = TestFramer::new(&TestFramerParams::new(params.0, params.1));
+    let message = tester.make_message();
+    let control_message =
+        if let MessageStructuredData::Control(control_message) = message.structured_data() {
+            control_message
+        } else {
+            return Err(Error::ErrInvalidMessageType(params.0 as u64));
+        };
+    let predicted = tester.framer.control_message_size(&control_message)?;
+    let mut buffer = vec![];
+    let size = tester
+        .framer
+        .serialize_control_message(control_message, &mut buffer)?;
+    assert_eq!(predicted, size);
+    assert_eq!(predicted, buffer.len());
+    Ok(())
+}
+
+#[test]
+fn test_control_message_size_checks_message() -> Result<()> {
+    let message = ControlMessage::ClientSetup(ClientSetup::for_quic(
+        vec![Version::Draft04],
+        Role::PubSub,
+        "p".repeat(MAX_MESSSAGE_HEADER_SIZE),
+    ));
+    assert!(matches!(
+        MessageFramer::new(Perspective::Client).control_message_size(&message),
+        Err(Error::ErrFrameError(_))
+    ));
+
+    let message = ControlMessage::ClientSetup(ClientSetup::for_quic(
+        vec![Version::Draft04],
+        Role::PubSub,
+        "p".to_string(),
+    ));
+    assert!(matches!(
+        MessageFramer::new(Perspective::Server).control_message_size(&message),
+        Err(Error::ErrFrameError(_))
+    ));
+    Ok(())
+}
+
 #[test]
 fn test_group_middler() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let header = TestStreamHeaderGroupMessage::new();
     let header_object_header =
         if let MessageStructuredData::Object(object_header) = header.structured_data() {
             object_header
         } else {
-            assert!(false);
             return Err(Error::ErrInvalidMessageType(0));
         };
 
     let mut buffer1 = vec![];
-    let buffer1_size = MessageFramer::serialize_object(
+    let buffer1_size = framer.serialize_object(
         header_object_header,
         true,
         Bytes::from_static(b"foo"),
@@ -139,11 +264,10 @@ fn test_group_middler() -> Result<()> {
         if let MessageStructuredData::Object(object_header) = middler.structured_data() {
             object_header
         } else {
-            assert!(false);
             return Err(Error::ErrInvalidMessageType(0));
         };
     let mut buffer2 = vec![];
-    let buffer2_size = MessageFramer::serialize_object(
+    let buffer2_size = framer.serialize_object(
         middler_object_header,
         false,
         Bytes::from_static(b"bar"),
@@ -157,16 +281,16 @@ fn test_group_middler() -> Result<()> {
 
 #[test]
 fn test_track_middler() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let header = TestStreamHeaderTrackMessage::new();
     let header_object_header =
         if let MessageStructuredData::Object(object_header) = header.structured_data() {
             object_header
         } else {
-            assert!(false);
             return Err(Error::ErrInvalidMessageType(0));
         };
     let mut buffer1 = vec![];
-    let buffer1_size = MessageFramer::serialize_object(
+    let buffer1_size = framer.serialize_object(
         header_object_header,
         true,
         Bytes::from_static(b"foo"),
@@ -181,11 +305,10 @@ fn test_track_middler() -> Result<()> {
         if let MessageStructuredData::Object(object_header) = middler.structured_data() {
             object_header
         } else {
-            assert!(false);
             return Err(Error::ErrInvalidMessageType(0));
         };
     let mut buffer2 = vec![];
-    let buffer2_size = MessageFramer::serialize_object(
+    let buffer2_size = framer.serialize_object(
         middler_object_header,
         false,
         Bytes::from_static(b"bar"),
@@ -199,6 +322,7 @@ fn test_track_middler() -> Result<()> {
 
 #[test]
 fn test_bad_object_input() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let mut object = ObjectHeader {
         subscribe_id: 3,
         track_alias: 4,
@@ -212,20 +336,26 @@ fn test_bad_object_input() -> Result<()> {
     let mut buffer = vec![];
     object.object_forwarding_preference = ObjectForwardingPreference::Datagram;
     assert!(
-        MessageFramer::serialize_object_header(object, false, &mut buffer).is_err(),
+        framer
+            .serialize_object_header(object, false, &mut buffer)
+            .is_err(),
         "must be first"
     );
     buffer.clear();
     object.object_forwarding_preference = ObjectForwardingPreference::Group;
     assert!(
-        MessageFramer::serialize_object_header(object, false, &mut buffer).is_err(),
+        framer
+            .serialize_object_header(object, false, &mut buffer)
+            .is_err(),
         "requires knowing the object length"
     );
     buffer.clear();
     object.object_payload_length = Some(5);
     object.object_status = ObjectStatus::EndOfGroup;
     assert!(
-        MessageFramer::serialize_object_header(object, false, &mut buffer).is_err(),
+        framer
+            .serialize_object_header(object, false, &mut buffer)
+            .is_err(),
         "Object status must be kNormal if payload is non-empty"
     );
     buffer.clear();
@@ -234,6 +364,7 @@ fn test_bad_object_input() -> Result<()> {
 
 #[test]
 fn test_datagram() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let datagram = TestObjectDatagramMessage::new();
     let object = ObjectHeader {
         subscribe_id: 3,
@@ -247,7 +378,7 @@ fn test_datagram() -> Result<()> {
     };
     let payload = Bytes::from_static(b"foo");
     let mut buffer = vec![];
-    let buffer_size = MessageFramer::serialize_object_datagram(object, payload, &mut buffer)?;
+    let buffer_size = framer.serialize_object_datagram(object, payload, &mut buffer)?;
     assert_eq!(buffer.len(), buffer_size);
     assert_eq!(buffer.len(), datagram.total_message_size());
     assert_eq!(&buffer[..], datagram.packet_sample());
@@ -255,29 +386,31 @@ fn test_datagram() -> Result<()> {
 }
 
 #[test]
+#[allow(clippy::unnecessary_unwrap)]
 fn test_all_subscribe_inputs() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     for start_group in [None, Some(4)] {
         for start_object in [None, Some(0)] {
             for end_group in [None, Some(7)] {
                 for end_object in [None, Some(3)] {
                     let expected_filter_type;
-                    if !start_group.is_some()
-                        && !start_object.is_some()
-                        && !end_group.is_some()
-                        && !end_object.is_some()
+                    if start_group.is_none()
+                        && start_object.is_none()
+                        && end_group.is_none()
+                        && end_object.is_none()
                     {
                         expected_filter_type = FilterType::LatestObject;
-                    } else if !start_group.is_some()
+                    } else if start_group.is_none()
                         && start_object.is_some()
                         && *start_object.as_ref().unwrap() == 0
-                        && !end_group.is_some()
-                        && !end_object.is_some()
+                        && end_group.is_none()
+                        && end_object.is_none()
                     {
                         expected_filter_type = FilterType::LatestGroup;
                     } else if start_group.is_some()
                         && start_object.is_some()
-                        && !end_group.is_some()
-                        && !end_object.is_some()
+                        && end_group.is_none()
+                        && end_object.is_none()
                     {
                         expected_filter_type = FilterType::AbsoluteStart(FullSequence {
                             group_id: start_group.unwrap(),
@@ -321,7 +454,7 @@ fn test_all_subscribe_inputs() -> Result<()> {
                         authorization_info: None,
                     };
                     let mut buffer = vec![];
-                    let _ = MessageFramer::serialize_control_message(
+                    let _ = framer.serialize_control_message(
                         ControlMessage::Subscribe(subscribe),
                         &mut buffer,
                     )?;
@@ -344,6 +477,7 @@ fn test_all_subscribe_inputs() -> Result<()> {
 
 #[test]
 fn test_subscribe_end_before_start() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let mut subscribe = Subscribe {
         subscribe_id: 3,
         track_alias: 4,
@@ -363,11 +497,9 @@ fn test_subscribe_end_before_start() -> Result<()> {
     };
     let mut buffer = vec![];
     assert!(
-        MessageFramer::serialize_control_message(
-            ControlMessage::Subscribe(subscribe.clone()),
-            &mut buffer,
-        )
-        .is_err(),
+        framer
+            .serialize_control_message(ControlMessage::Subscribe(subscribe.clone()), &mut buffer,)
+            .is_err(),
         "Invalid object range"
     );
     buffer.clear();
@@ -382,10 +514,8 @@ fn test_subscribe_end_before_start() -> Result<()> {
         },
     );
     assert!(
-        MessageFramer::serialize_control_message(
-            ControlMessage::Subscribe(subscribe),
-            &mut buffer,
-        )
+        framer
+            .serialize_control_message(ControlMessage::Subscribe(subscribe), &mut buffer,)
             .is_err(),
         "Invalid object range"
     );
@@ -395,6 +525,7 @@ fn test_subscribe_end_before_start() -> Result<()> {
 
 #[test]
 fn test_subscribe_latest_group_nonzero_object() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let subscribe = Subscribe {
         subscribe_id: 3,
         track_alias: 4,
@@ -408,10 +539,8 @@ fn test_subscribe_latest_group_nonzero_object() -> Result<()> {
     };
     let mut buffer = vec![];
     assert!(
-        MessageFramer::serialize_control_message(
-            ControlMessage::Subscribe(subscribe),
-            &mut buffer,
-        )
+        framer
+            .serialize_control_message(ControlMessage::Subscribe(subscribe), &mut buffer,)
             .is_err(),
         "Invalid object range"
     );
@@ -420,6 +549,7 @@ fn test_subscribe_latest_group_nonzero_object() -> Result<()> {
 
 #[test]
 fn test_subscribe_update_end_group_only() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let subscribe_update = SubscribeUpdate {
         subscribe_id: 3,
         start_group_object: FullSequence {
@@ -433,7 +563,7 @@ fn test_subscribe_update_end_group_only() -> Result<()> {
         authorization_info: Some("bar".to_string()),
     };
     let mut buffer = vec![];
-    let _ = MessageFramer::serialize_control_message(
+    let _ = framer.serialize_control_message(
         ControlMessage::SubscribeUpdate(subscribe_update),
         &mut buffer,
     )?;
@@ -447,6 +577,7 @@ fn test_subscribe_update_end_group_only() -> Result<()> {
 
 #[test]
 fn test_subscribe_update_increments_end() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let subscribe_update = SubscribeUpdate {
         subscribe_id: 3,
         start_group_object: FullSequence {
@@ -460,7 +591,7 @@ fn test_subscribe_update_increments_end() -> Result<()> {
         authorization_info: Some("bar".to_string()),
     };
     let mut buffer = vec![];
-    let _ = MessageFramer::serialize_control_message(
+    let _ = framer.serialize_control_message(
         ControlMessage::SubscribeUpdate(subscribe_update),
         &mut buffer,
     )?;
@@ -474,6 +605,7 @@ fn test_subscribe_update_increments_end() -> Result<()> {
 
 #[test]
 fn test_subscribe_update_invalid_range() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
     let subscribe_update = SubscribeUpdate {
         subscribe_id: 3,
         start_group_object: FullSequence {
@@ -488,12 +620,410 @@ fn test_subscribe_update_invalid_range() -> Result<()> {
     };
     let mut buffer = vec![];
     assert!(
-        MessageFramer::serialize_control_message(
-            ControlMessage::SubscribeUpdate(subscribe_update),
-            &mut buffer,
-        )
-        .is_err(),
+        framer
+            .serialize_control_message(
+                ControlMessage::SubscribeUpdate(subscribe_update),
+                &mut buffer,
+            )
+            .is_err(),
         "Invalid object range"
     );
     Ok(())
 }
+
+#[test]
+fn test_client_setup_draft_version_round_trip() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let client_setup = ClientSetup {
+        supported_versions: vec![Version::Draft04],
+        role: Some(Role::PubSub),
+        path: Some("foo".to_string()),
+        ..Default::default()
+    };
+    let mut buffer = vec![];
+    framer.serialize_control_message(
+        ControlMessage::ClientSetup(client_setup.clone()),
+        &mut buffer,
+    )?;
+    // 0xff000004 does not fit in 4 bytes, so it takes an 8-byte varint.
+    let expected_version: &[u8] = &[0xc0, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x04];
+    assert_eq!(&buffer[2..3], &[0x01]);
+    assert_eq!(&buffer[3..11], expected_version);
+
+    let mut parser = MessageParser::new(false);
+    parser.process_data(&mut &buffer[..], false);
+    let parsed =
+        if let Some(MessageParserEvent::ControlMessage(ControlMessage::ClientSetup(parsed))) =
+            parser.poll_event()
+        {
+            parsed
+        } else {
+            return Err(Error::ErrInvalidMessageType(
+                MessageType::ClientSetup as u64,
+            ));
+        };
+    assert_eq!(parsed.supported_versions, vec![Version::Draft04]);
+    assert_eq!(parsed, client_setup);
+    assert!(parser.poll_event().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_group_stream_round_trip() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let first = ObjectHeader {
+        subscribe_id: 3,
+        track_alias: 4,
+        group_id: 5,
+        object_id: 6,
+        object_send_order: 7,
+        object_status: ObjectStatus::Normal,
+        object_forwarding_preference: ObjectForwardingPreference::Group,
+        object_payload_length: None,
+    };
+    let mut second = first;
+    second.object_id = 7;
+    let mut last = first;
+    last.object_id = 8;
+    last.object_status = ObjectStatus::EndOfGroup;
+    let objects = [
+        (first, Bytes::from_static(b"foo")),
+        (second, Bytes::from_static(b"bar")),
+        (last, Bytes::new()),
+    ];
+
+    let mut buffer = vec![];
+    let buffer_size = framer.serialize_group_stream(&objects, &mut buffer)?;
+    assert_eq!(buffer.len(), buffer_size);
+
+    let mut parser = MessageParser::new(false);
+    parser.process_data(&mut &buffer[..], true);
+    for (object_header, payload) in objects.iter() {
+        if let Some(MessageParserEvent::ObjectMessage(
+            parsed_header,
+            parsed_payload,
+            end_of_message,
+        )) = parser.poll_event()
+        {
+            assert_eq!(parsed_header.object_id, object_header.object_id);
+            assert_eq!(parsed_header.group_id, object_header.group_id);
+            assert_eq!(parsed_header.object_status, object_header.object_status);
+            assert_eq!(&parsed_payload, payload);
+            assert!(end_of_message);
+        } else {
+            return Err(Error::ErrInvalidMessageType(
+                MessageType::StreamHeaderGroup as u64,
+            ));
+        }
+    }
+    assert!(parser.poll_event().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_group_stream_mismatched_group() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let first = ObjectHeader {
+        object_forwarding_preference: ObjectForwardingPreference::Group,
+        group_id: 5,
+        ..Default::default()
+    };
+    let mut other = first;
+    other.group_id = 6;
+    let mut buffer = vec![];
+    assert!(framer
+        .serialize_group_stream(&[(first, Bytes::new()), (other, Bytes::new())], &mut buffer)
+        .is_err());
+    assert!(framer.serialize_group_stream(&[], &mut buffer).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_client_framer_refuses_server_setup() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let mut buffer = vec![];
+    let server_setup = ControlMessage::ServerSetup(ServerSetup {
+        supported_version: Version::Draft04,
+        role: Some(Role::PubSub),
+    });
+    assert!(matches!(
+        framer.serialize_control_message(server_setup.clone(), &mut buffer),
+        Err(Error::ErrFrameError(_))
+    ));
+    assert!(buffer.is_empty());
+
+    let framer = MessageFramer::new(Perspective::Server);
+    framer.serialize_control_message(server_setup, &mut buffer)?;
+    assert!(!buffer.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_server_framer_refuses_client_setup() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Server);
+    let mut buffer = vec![];
+    let client_setup = ControlMessage::ClientSetup(ClientSetup {
+        supported_versions: vec![Version::Draft04],
+        role: Some(Role::PubSub),
+        path: Some("foo".to_string()),
+        uses_web_transport: false,
+    });
+    assert!(matches!(
+        framer.serialize_control_message(client_setup, &mut buffer),
+        Err(Error::ErrFrameError(_))
+    ));
+    assert!(buffer.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_serialize_control_into_slice() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let message = create_test_message(MessageType::Subscribe, false);
+    let control_message =
+        if let MessageStructuredData::Control(control_message) = message.structured_data() {
+            control_message
+        } else {
+            return Err(Error::ErrInvalidMessageType(MessageType::Subscribe as u64));
+        };
+
+    let mut expected = vec![];
+    framer.serialize_control_message(control_message.clone(), &mut expected)?;
+
+    let mut storage = [0u8; 64];
+    let mut w = &mut storage[..];
+    let size = framer.serialize_control_into(&mut w, &control_message)?;
+    assert_eq!(size, expected.len());
+    assert_eq!(&storage[..size], &expected[..]);
+
+    // A send buffer that is too small is reported rather than overrun, and
+    // none of the message is written into it.
+    let mut storage = [0xaau8; 4];
+    let mut w = &mut storage[..];
+    assert!(matches!(
+        framer.serialize_control_into(&mut w, &control_message),
+        Err(Error::ErrBufferTooShort)
+    ));
+    assert_eq!(storage, [0xaa; 4]);
+    Ok(())
+}
+
+#[test]
+fn test_serialize_object_into_short_slice() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let object = ObjectHeader {
+        object_forwarding_preference: ObjectForwardingPreference::Object,
+        ..Default::default()
+    };
+    let mut storage = [0u8; 8];
+    let mut w = &mut storage[..];
+    assert!(matches!(
+        framer.serialize_object(object, true, Bytes::from_static(b"0123456789"), &mut w),
+        Err(Error::ErrBufferTooShort)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_track_stream_round_trip() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let header = ObjectHeader {
+        subscribe_id: 3,
+        track_alias: 4,
+        group_id: 5,
+        object_id: 6,
+        object_send_order: 7,
+        object_status: ObjectStatus::Normal,
+        object_forwarding_preference: ObjectForwardingPreference::Track,
+        object_payload_length: None,
+    };
+    let mut follow_on = header;
+    follow_on.group_id = 9;
+    follow_on.object_id = 0;
+
+    let mut buffer = vec![];
+    framer.serialize_object(header, true, Bytes::from_static(b"foo"), &mut buffer)?;
+    framer.serialize_object(follow_on, false, Bytes::from_static(b"bar"), &mut buffer)?;
+
+    let mut parser = MessageParser::new(false);
+    parser.process_data(&mut &buffer[..], true);
+    for (expected, expected_payload) in [(header, &b"foo"[..]), (follow_on, &b"bar"[..])] {
+        if let Some(MessageParserEvent::ObjectMessage(parsed, payload, true)) = parser.poll_event()
+        {
+            assert_eq!(parsed.subscribe_id, expected.subscribe_id);
+            assert_eq!(parsed.track_alias, expected.track_alias);
+            assert_eq!(parsed.group_id, expected.group_id);
+            assert_eq!(parsed.object_id, expected.object_id);
+            assert_eq!(parsed.object_send_order, expected.object_send_order);
+            assert_eq!(&payload[..], expected_payload);
+        } else {
+            return Err(Error::ErrInvalidMessageType(
+                MessageType::StreamHeaderTrack as u64,
+            ));
+        }
+    }
+    assert!(parser.poll_event().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_datagram_exceeds_max_size() -> Result<()> {
+    let mut framer = MessageFramer::new(Perspective::Server);
+    framer.set_max_datagram_size(Some(10));
+    let object = ObjectHeader {
+        subscribe_id: 3,
+        track_alias: 4,
+        group_id: 5,
+        object_id: 6,
+        object_send_order: 7,
+        object_status: ObjectStatus::Normal,
+        object_forwarding_preference: ObjectForwardingPreference::Datagram,
+        object_payload_length: None,
+    };
+
+    // 7 bytes of header plus 3 of payload fit exactly.
+    let mut buffer = vec![];
+    let size = framer.serialize_object_datagram(object, Bytes::from_static(b"foo"), &mut buffer)?;
+    assert_eq!(size, 10);
+
+    let mut buffer = vec![];
+    assert!(matches!(
+        framer.serialize_object_datagram(object, Bytes::from_static(b"foobar"), &mut buffer),
+        Err(Error::ErrFrameError(_))
+    ));
+    assert!(buffer.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_follow_on_status_object() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    for forwarding_preference in [
+        ObjectForwardingPreference::Track,
+        ObjectForwardingPreference::Group,
+    ] {
+        let mut object = ObjectHeader {
+            subscribe_id: 3,
+            track_alias: 4,
+            group_id: 5,
+            object_id: 6,
+            object_send_order: 7,
+            object_status: ObjectStatus::EndOfGroup,
+            object_forwarding_preference: forwarding_preference,
+            object_payload_length: Some(3),
+        };
+        let mut buffer = vec![];
+        assert!(matches!(
+            framer.serialize_object_header(object, false, &mut buffer),
+            Err(Error::ErrInvalidObjectType(_))
+        ));
+        assert!(buffer.is_empty());
+
+        // With no payload, the status follows the zero length.
+        object.object_payload_length = Some(0);
+        framer.serialize_object_header(object, false, &mut buffer)?;
+        assert_eq!(buffer.last(), Some(&(ObjectStatus::EndOfGroup as u8)));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_serialize_control_to_bytes_reserves() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let message = ControlMessage::ClientSetup(ClientSetup::for_quic(
+        vec![Version::Draft04; 32],
+        Role::PubSub,
+        "p".repeat(1000),
+    ));
+    let size = framer.control_message_size(&message)?;
+
+    let mut buffer = BytesMut::new();
+    assert_eq!(
+        framer.serialize_control_to_bytes(message.clone(), &mut buffer)?,
+        size
+    );
+    assert_eq!(buffer.len(), size);
+    assert!(buffer.capacity() >= size);
+
+    // Reusing the buffer does not reallocate.
+    buffer.clear();
+    let ptr = buffer.as_ptr();
+    framer.serialize_control_to_bytes(message, &mut buffer)?;
+    assert_eq!(buffer.as_ptr(), ptr);
+    assert_eq!(buffer.len(), size);
+    Ok(())
+}
+
+#[test]
+fn test_control_message_too_large() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let mut buffer = vec![];
+    let message = ControlMessage::ClientSetup(ClientSetup::for_quic(
+        vec![Version::Draft04],
+        Role::PubSub,
+        "p".repeat(MAX_MESSSAGE_HEADER_SIZE),
+    ));
+    assert!(matches!(
+        framer.serialize_control_into(&mut buffer, &message),
+        Err(Error::ErrFrameError(_))
+    ));
+    assert!(buffer.is_empty());
+    assert!(matches!(
+        framer.serialize_control_message(message.clone(), &mut buffer),
+        Err(Error::ErrFrameError(_))
+    ));
+    assert!(buffer.is_empty());
+
+    // serialize_control_to_bytes leaves what was already in the buffer.
+    let mut bytes = BytesMut::from(&b"abc"[..]);
+    assert!(matches!(
+        framer.serialize_control_to_bytes(message, &mut bytes),
+        Err(Error::ErrFrameError(_))
+    ));
+    assert_eq!(&bytes[..], b"abc");
+
+    let mut buffer = vec![];
+
+    let message = ControlMessage::ClientSetup(ClientSetup::for_quic(
+        vec![Version::Draft04],
+        Role::PubSub,
+        "p".repeat(MAX_MESSSAGE_HEADER_SIZE / 2),
+    ));
+    framer.serialize_control_into(&mut buffer, &message)?;
+    Ok(())
+}
+
+#[test]
+fn test_serialize_raw_parameters() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Client);
+    let parameters = framer.serialize_raw_parameters(&[
+        (0x20, Bytes::from_static(b"x")),
+        (0x02, Bytes::from_static(b"bar")),
+        (0x21, Bytes::new()),
+    ])?;
+    assert_eq!(
+        &parameters[..],
+        &[
+            0x03, // 3 parameters
+            0x02, 0x03, 0x62, 0x61, 0x72, // authorization_info = "bar"
+            0x20, 0x01, 0x78, // unknown = "x"
+            0x21, 0x00, // unknown = ""
+        ]
+    );
+
+    let mut packet = vec![
+        0x03, 0x01, 0x02, // id and alias
+        0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+        0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
+        0x01, // Filter type: Latest Group
+    ];
+    packet.extend_from_slice(&parameters);
+    let (message, len) = MessageParser::parse_control_message(&mut &packet[..], false)?;
+    assert_eq!(len, packet.len());
+    if let ControlMessage::Subscribe(subscribe) = message {
+        assert_eq!(subscribe.authorization_info, Some("bar".to_string()));
+    } else {
+        return Err(Error::ErrInvalidMessageType(MessageType::Subscribe as u64));
+    }
+    Ok(())
+}
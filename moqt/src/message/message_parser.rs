@@ -1,11 +1,20 @@
+use crate::message::message_framer::MessageFramer;
 use crate::message::object::{ObjectForwardingPreference, ObjectHeader, ObjectStatus};
-use crate::message::{ControlMessage, MessageType, MAX_MESSSAGE_HEADER_SIZE};
-use crate::serde::Deserializer;
+use crate::message::{
+    ControlMessage, MessageType, MAX_BUFFERED_OBJECT_SIZE, MAX_MESSSAGE_HEADER_SIZE,
+};
+use crate::serde::varint::VarInt;
+use crate::serde::{read_at_most, Deserializer};
 use crate::{Error, Result};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
+use log::trace;
 use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ErrorCode {
     #[default]
@@ -24,12 +33,101 @@ impl Display for ErrorCode {
     }
 }
 
+/// Why a CLIENT_SETUP was rejected for breaking the PATH rules of its transport.
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SetupViolation {
+    PathOverWebTransport,
+    MissingPathOverQuic,
+}
+
+impl Display for SetupViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            SetupViolation::PathOverWebTransport => {
+                write!(
+                    f,
+                    "WebTransport connection is using PATH parameter in SETUP"
+                )
+            }
+            SetupViolation::MissingPathOverQuic => {
+                write!(
+                    f,
+                    "PATH SETUP parameter missing from Client message over QUIC"
+                )
+            }
+        }
+    }
+}
+
 pub enum MessageParserEvent {
     ParsingError(ErrorCode, String),
     ObjectMessage(ObjectHeader, Bytes, bool),
     ControlMessage(ControlMessage),
 }
 
+/// Feeds arbitrary bytes to a fresh parser in pseudo-random chunk sizes,
+/// then signals FIN. The chunking is seeded from the input, so a failing
+/// input replays identically. Panics if the parser ends up neither having
+/// reported an error nor drained its buffer.
+pub fn fuzz_control_parser(data: &[u8], uses_web_transport: bool) {
+    let mut parser = MessageParser::new(uses_web_transport);
+    let mut seed = (data.len() as u64) ^ 0x9e37_79b9_7f4a_7c15;
+    let mut offset = 0;
+    let mut parsing_error = false;
+    while offset < data.len() {
+        // xorshift64
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let chunk = 1 + (seed % 16) as usize;
+        let end = std::cmp::min(offset + chunk, data.len());
+        parser.process_data(&mut &data[offset..end], end == data.len());
+        offset = end;
+        while let Some(event) = parser.poll_event() {
+            if let MessageParserEvent::ParsingError(_, _) = event {
+                parsing_error = true;
+            }
+        }
+    }
+    assert!(
+        parsing_error || data.is_empty() || !parser.has_pending(),
+        "parser holds {} bytes after FIN without reporting an error",
+        parser.buffered_len()
+    );
+}
+
+impl MessageParserEvent {
+    /// Frames the event back into bytes, e.g. for a relay forwarding what it
+    /// parsed. Stream objects are refused, since their framing depends on
+    /// where they sit in the stream; parsing errors are returned as errors.
+    pub fn reserialize(&self, framer: &MessageFramer) -> Result<BytesMut> {
+        let mut buf = BytesMut::new();
+        match self {
+            MessageParserEvent::ParsingError(code, reason) => {
+                return Err(Error::ErrParseError(*code, reason.clone()));
+            }
+            MessageParserEvent::ControlMessage(control_message) => {
+                framer.serialize_control_into(&mut buf, control_message)?;
+            }
+            MessageParserEvent::ObjectMessage(object_header, payload, _) => {
+                if object_header.object_forwarding_preference
+                    != ObjectForwardingPreference::Datagram
+                {
+                    return Err(Error::ErrFrameError(
+                        "Cannot reserialize an object received on a stream".to_string(),
+                    ));
+                }
+                framer.serialize_object_datagram(*object_header, payload.clone(), &mut buf)?;
+            }
+        }
+        Ok(buf)
+    }
+}
+
 pub struct MessageParser {
     uses_web_transport: bool,
     no_more_data: bool, // Fatal error or fin. No more parsing.
@@ -50,7 +148,30 @@ pub struct MessageParser {
     object_metadata: Option<ObjectHeader>,
     payload_length_remaining: usize,
 
-    parser_events: VecDeque<MessageParserEvent>,
+    // If false, payload chunks are held in partial_object until the object is
+    // complete, up to max_object_size bytes.
+    deliver_partial_objects: bool,
+    max_object_size: usize,
+    partial_object: Option<BytesMut>,
+
+    // If true, a zero-length AUTHORIZATION_INFO is a protocol violation.
+    reject_empty_auth_info: bool,
+
+    // If true, a GOAWAY whose new_session_uri does not look like a URI is a
+    // protocol violation.
+    validate_go_away_uri: bool,
+
+    // Parsing pauses once this many events are waiting to be polled. While
+    // paused, buffered data may hold complete messages, so a FIN is not checked
+    // against it until parsing resumes.
+    max_events: usize,
+    paused: bool,
+
+    // If true, the wire image of each control message is queued with its
+    // ControlMessage event.
+    capture_raw: bool,
+
+    parser_events: VecDeque<(MessageParserEvent, Option<Bytes>)>,
 }
 
 impl MessageParser {
@@ -64,6 +185,19 @@ impl MessageParser {
             object_metadata: None,
             payload_length_remaining: 0,
 
+            deliver_partial_objects: true,
+            max_object_size: MAX_BUFFERED_OBJECT_SIZE,
+            partial_object: None,
+
+            reject_empty_auth_info: false,
+
+            validate_go_away_uri: false,
+
+            max_events: usize::MAX,
+            paused: false,
+
+            capture_raw: false,
+
             parser_events: VecDeque::new(),
         }
     }
@@ -76,6 +210,63 @@ impl MessageParser {
     /// error.
     /// Any calls after sending |fin| = true will be ignored.
     pub fn process_data<R: Buf>(&mut self, buf: &mut R, fin: bool) {
+        let first_new_event = self.parser_events.len();
+        self.process_stream_data(buf, fin);
+        if !self.deliver_partial_objects {
+            self.coalesce_object_events(first_new_event);
+        }
+    }
+
+    /// Whether OBJECT payload is delivered in chunks as it arrives (the
+    /// default), or buffered and delivered once the object is complete.
+    pub fn set_deliver_partial_objects(&mut self, deliver_partial_objects: bool) {
+        self.deliver_partial_objects = deliver_partial_objects;
+    }
+
+    /// Limits the payload buffered for a single object when partial objects
+    /// are not delivered. Exceeding it is a parsing error.
+    pub fn set_max_object_size(&mut self, max_object_size: usize) {
+        self.max_object_size = max_object_size;
+    }
+
+    /// Whether a zero-length AUTHORIZATION_INFO parameter is rejected. By
+    /// default it is accepted as an empty string.
+    pub fn set_reject_empty_auth_info(&mut self, reject_empty_auth_info: bool) {
+        self.reject_empty_auth_info = reject_empty_auth_info;
+    }
+
+    /// Whether the new_session_uri of a GOAWAY must be empty or have a scheme
+    /// and no control characters. By default any UTF-8 string is accepted.
+    pub fn set_validate_go_away_uri(&mut self, validate_go_away_uri: bool) {
+        self.validate_go_away_uri = validate_go_away_uri;
+    }
+
+    /// Caps the number of events waiting to be polled. Once reached, parsing
+    /// stops and the remaining data stays buffered; call resume() after
+    /// draining events to continue.
+    pub fn set_max_events(&mut self, max_events: usize) {
+        self.max_events = max_events;
+    }
+
+    /// Whether to keep the exact bytes of each control message, returned by
+    /// poll_event_with_raw().
+    pub fn set_capture_raw(&mut self, capture_raw: bool) {
+        self.capture_raw = capture_raw;
+    }
+
+    /// Continues parsing data left buffered when the event cap was reached.
+    pub fn resume(&mut self) {
+        if self.parsing_error {
+            return;
+        }
+        let first_new_event = self.parser_events.len();
+        self.process_buffered_messages(self.no_more_data);
+        if !self.deliver_partial_objects {
+            self.coalesce_object_events(first_new_event);
+        }
+    }
+
+    fn process_stream_data<R: Buf>(&mut self, buf: &mut R, fin: bool) {
         if self.no_more_data {
             self.parse_error(
                 ErrorCode::ProtocolViolation,
@@ -83,9 +274,12 @@ impl MessageParser {
             );
         }
 
-        // Check for early fin
+        // Check for early fin. While paused, the incomplete-message checks run
+        // in process_buffered_messages once the buffered messages are parsed.
         if fin {
             self.no_more_data = true;
+        }
+        if fin && !self.paused {
             if self.object_payload_in_progress() && self.payload_length_remaining > buf.remaining()
             {
                 self.parse_error(
@@ -113,13 +307,15 @@ impl MessageParser {
                 // This is additional payload for an OBJECT.
                 if object_metadata.object_payload_length.is_none() {
                     // Deliver the data and exit.
-                    self.parser_events
-                        .push_back(MessageParserEvent::ObjectMessage(
+                    self.parser_events.push_back((
+                        MessageParserEvent::ObjectMessage(
                             *object_metadata,
                             self.buffered_message
                                 .copy_to_bytes(self.buffered_message.remaining()),
                             fin,
-                        ));
+                        ),
+                        None,
+                    ));
                     if fin {
                         self.object_metadata = None;
                     }
@@ -128,28 +324,42 @@ impl MessageParser {
                 if self.buffered_message.remaining() < self.payload_length_remaining {
                     // Does not finish the payload; deliver and exit.
                     self.payload_length_remaining -= self.buffered_message.remaining();
-                    self.parser_events
-                        .push_back(MessageParserEvent::ObjectMessage(
+                    self.parser_events.push_back((
+                        MessageParserEvent::ObjectMessage(
                             *object_metadata,
                             self.buffered_message
                                 .copy_to_bytes(self.buffered_message.remaining()),
                             false,
-                        ));
+                        ),
+                        None,
+                    ));
                     return;
                 }
                 // Finishes the payload. Deliver and continue.
-                self.parser_events
-                    .push_back(MessageParserEvent::ObjectMessage(
+                self.parser_events.push_back((
+                    MessageParserEvent::ObjectMessage(
                         *object_metadata,
                         self.buffered_message
                             .copy_to_bytes(self.payload_length_remaining),
                         true,
-                    ));
+                    ),
+                    None,
+                ));
                 self.payload_length_remaining = 0; // Expect a new object.
             }
         }
 
+        self.process_buffered_messages(fin);
+    }
+
+    fn process_buffered_messages(&mut self, fin: bool) {
         while self.buffered_message.has_remaining() {
+            if self.parser_events.len() >= self.max_events {
+                // Leave the rest buffered until the caller drains and resumes.
+                self.paused = true;
+                return;
+            }
+            self.paused = false;
             let message_len = self.process_message(fin);
             if message_len == 0 {
                 if self.buffered_message.remaining() > MAX_MESSSAGE_HEADER_SIZE {
@@ -184,10 +394,60 @@ impl MessageParser {
         Ok((object_header, r.copy_to_bytes(r.remaining())))
     }
 
+    /// Parses exactly one complete control message, for callers that already
+    /// hold the whole message. Returns the message and the bytes consumed; an
+    /// incomplete message is an error rather than buffered.
+    pub fn parse_control_message<R: Buf>(
+        r: &mut R,
+        uses_web_transport: bool,
+    ) -> Result<(ControlMessage, usize)> {
+        let (mut control_message, message_len) = ControlMessage::deserialize(r)?;
+        if let ControlMessage::ClientSetup(client_setup) = &mut control_message {
+            if uses_web_transport && client_setup.path.is_some() {
+                return Err(Error::ErrSetupViolation(
+                    SetupViolation::PathOverWebTransport,
+                ));
+            } else if !uses_web_transport && client_setup.path.is_none() {
+                return Err(Error::ErrSetupViolation(
+                    SetupViolation::MissingPathOverQuic,
+                ));
+            }
+            client_setup.uses_web_transport = uses_web_transport;
+        }
+        Ok((control_message, message_len))
+    }
+
     pub fn poll_event(&mut self) -> Option<MessageParserEvent> {
+        self.parser_events.pop_front().map(|(event, _)| event)
+    }
+
+    /// Like poll_event(), but also returns the wire image of a control
+    /// message if capture_raw was set when it was parsed.
+    pub fn poll_event_with_raw(&mut self) -> Option<(MessageParserEvent, Option<Bytes>)> {
         self.parser_events.pop_front()
     }
 
+    /// Number of bytes held back while waiting for the rest of a message.
+    pub fn buffered_len(&self) -> usize {
+        self.buffered_message.len()
+    }
+
+    /// Whether a partial message is buffered, so more data is needed from the
+    /// transport to complete it.
+    pub fn has_pending(&self) -> bool {
+        !self.buffered_message.is_empty()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn no_more_data(&self) -> bool {
+        self.no_more_data
+    }
+
+    #[cfg(test)]
+    pub(crate) fn parsing_error(&self) -> bool {
+        self.parsing_error
+    }
+
     fn process_message(&mut self, fin: bool) -> usize {
         if self.object_stream_initialized() && !self.object_payload_in_progress() {
             // This is a follow-on object in a stream.
@@ -200,9 +460,13 @@ impl MessageParser {
                 );
             }
         }
-        let mut mt_reader = self.buffered_message.as_ref();
-        let message_type = match MessageType::deserialize(&mut mt_reader) {
-            Ok((message_type, _)) => message_type,
+        let message_type = match VarInt::peek(&self.buffered_message) {
+            Some((message_type, _)) => message_type.into_inner(),
+            None => return 0,
+        };
+        trace!("parsing message of type {:#x}", message_type);
+        let message_type = match MessageType::try_from(message_type) {
+            Ok(message_type) => message_type,
             Err(err) => {
                 if let Error::ErrParseError(code, reason) = err {
                     self.parse_error(code, reason);
@@ -224,29 +488,14 @@ impl MessageParser {
             self.process_object(message_type, fin)
         } else {
             let mut msg_reader = self.buffered_message.as_ref();
-            let (control_message, message_len) = match ControlMessage::deserialize(&mut msg_reader)
-            {
-                Ok((mut control_message, message_len)) => {
-                    if let ControlMessage::ClientSetup(client_setup) = &mut control_message {
-                        if self.uses_web_transport && client_setup.path.is_some() {
-                            self.parse_error(
-                                ErrorCode::ProtocolViolation,
-                                "WebTransport connection is using PATH parameter in SETUP"
-                                    .to_string(),
-                            );
-                            return 0;
-                        } else if !self.uses_web_transport && client_setup.path.is_none() {
-                            self.parse_error(
-                                ErrorCode::ProtocolViolation,
-                                "PATH SETUP parameter missing from Client message over QUIC"
-                                    .to_string(),
-                            );
-                            return 0;
-                        }
-                        client_setup.uses_web_transport = self.uses_web_transport;
-                    }
-
-                    (control_message, message_len)
+            let (control_message, message_len) = match MessageParser::parse_control_message(
+                &mut msg_reader,
+                self.uses_web_transport,
+            ) {
+                Ok((control_message, message_len)) => (control_message, message_len),
+                Err(Error::ErrSetupViolation(violation)) => {
+                    self.parse_error(ErrorCode::ProtocolViolation, violation.to_string());
+                    return 0;
                 }
                 Err(err) => {
                     if let Error::ErrParseError(code, reason) = err {
@@ -255,8 +504,39 @@ impl MessageParser {
                     return 0;
                 }
             };
+            if self.reject_empty_auth_info {
+                let authorization_info = match &control_message {
+                    ControlMessage::Subscribe(subscribe) => subscribe.authorization_info.as_deref(),
+                    ControlMessage::SubscribeUpdate(subscribe_update) => {
+                        subscribe_update.authorization_info.as_deref()
+                    }
+                    ControlMessage::Announce(announce) => announce.authorization_info.as_deref(),
+                    _ => None,
+                };
+                if authorization_info == Some("") {
+                    self.parse_error(
+                        ErrorCode::ProtocolViolation,
+                        "Empty AUTHORIZATION_INFO parameter".to_string(),
+                    );
+                    return 0;
+                }
+            }
+            if self.validate_go_away_uri {
+                if let ControlMessage::GoAway(go_away) = &control_message {
+                    if !go_away.has_plausible_uri() {
+                        self.parse_error(
+                            ErrorCode::ProtocolViolation,
+                            "GOAWAY new_session_uri is not a valid URI".to_string(),
+                        );
+                        return 0;
+                    }
+                }
+            }
+            let raw = self
+                .capture_raw
+                .then(|| Bytes::copy_from_slice(&self.buffered_message[..message_len]));
             self.parser_events
-                .push_back(MessageParserEvent::ControlMessage(control_message));
+                .push_back((MessageParserEvent::ControlMessage(control_message), raw));
             message_len
         }
     }
@@ -318,14 +598,13 @@ impl MessageParser {
             (0, 0)
         };
         let (object_send_order, osol) = u64::deserialize(r)?;
-        let (status, osl) = if message_type == MessageType::ObjectStream
+        let (object_status, osl) = if message_type == MessageType::ObjectStream
             || message_type == MessageType::ObjectDatagram
         {
-            u64::deserialize(r)?
+            ObjectStatus::deserialize(r)?
         } else {
-            (0, 0)
+            (ObjectStatus::Normal, 0)
         };
-        let object_status: ObjectStatus = status.into();
         let object_forwarding_preference: ObjectForwardingPreference =
             message_type.get_object_forwarding_preference()?;
 
@@ -345,7 +624,7 @@ impl MessageParser {
     }
 
     fn process_object_payload<R: Buf>(
-        parser_events: &mut VecDeque<MessageParserEvent>,
+        parser_events: &mut VecDeque<(MessageParserEvent, Option<Bytes>)>,
         object_header: &mut Option<ObjectHeader>,
         payload_length_remaining: &mut usize,
         r: &mut R,
@@ -372,27 +651,21 @@ impl MessageParser {
             let (object_payload_length, opl) = u64::deserialize(r)?;
             total_len += opl;
 
-            let mut status = 0; // Defaults to kNormal.
+            let mut status = ObjectStatus::Normal;
             if object_payload_length == 0 {
                 let sl;
-                (status, sl) = u64::deserialize(r)?;
+                (status, sl) = ObjectStatus::deserialize(r)?;
                 total_len += sl;
             }
 
             if let Some(object_metadata) = object_header.as_mut() {
                 object_metadata.object_id = object_id;
                 object_metadata.object_payload_length = Some(object_payload_length);
-                object_metadata.object_status = status.into();
+                object_metadata.object_status = status;
             }
         }
 
         if let Some(object_metadata) = object_header.as_ref() {
-            if object_metadata.object_status == ObjectStatus::Invalid {
-                return Err(Error::ErrParseError(
-                    ErrorCode::ProtocolViolation,
-                    "Invalid object status".to_string(),
-                ));
-            }
             if object_metadata.object_status != ObjectStatus::Normal {
                 // It is impossible to express an explicit length with this status.
                 if (message_type == MessageType::ObjectStream
@@ -405,10 +678,9 @@ impl MessageParser {
                         "Object with non-normal status has payload".to_string(),
                     ));
                 }
-                parser_events.push_back(MessageParserEvent::ObjectMessage(
-                    *object_metadata,
-                    Bytes::new(),
-                    true,
+                parser_events.push_back((
+                    MessageParserEvent::ObjectMessage(*object_metadata, Bytes::new(), true),
+                    None,
                 ));
                 return Ok(total_len);
             }
@@ -421,7 +693,6 @@ impl MessageParser {
             } else {
                 0
             };
-            let mut payload_to_draw = r.remaining();
             if fin && has_length && payload_length > r.remaining() {
                 return Err(Error::ErrParseError(
                     ErrorCode::ProtocolViolation,
@@ -429,18 +700,28 @@ impl MessageParser {
                 ));
             }
             let received_complete_message = fin || (has_length && payload_length <= r.remaining());
-            if received_complete_message && has_length && payload_length < r.remaining() {
-                payload_to_draw = payload_length;
-            }
+            // Without a length, all buffered data belongs to this object.
+            let payload = read_at_most(
+                r,
+                if has_length {
+                    payload_length
+                } else {
+                    usize::MAX
+                },
+            );
+            let payload_to_draw = payload.len();
             // The error case where there's a fin before the explicit length is complete
             // is handled in ProcessData() in two separate places. Even though the
             // message is "done" if fin regardless of has_length, it's bad to report to
             // the application that the object is done if it hasn't reached the promised
             // length.
-            parser_events.push_back(MessageParserEvent::ObjectMessage(
-                *object_metadata,
-                r.copy_to_bytes(payload_to_draw),
-                received_complete_message,
+            parser_events.push_back((
+                MessageParserEvent::ObjectMessage(
+                    *object_metadata,
+                    payload,
+                    received_complete_message,
+                ),
+                None,
             ));
             *payload_length_remaining = if has_length {
                 payload_length - payload_to_draw
@@ -454,14 +735,71 @@ impl MessageParser {
         Ok(total_len)
     }
 
+    // Replaces the OBJECT chunks queued since first_new_event with one event per
+    // complete object, carrying over any payload from previous calls.
+    fn coalesce_object_events(&mut self, first_new_event: usize) {
+        let events: Vec<(MessageParserEvent, Option<Bytes>)> =
+            self.parser_events.drain(first_new_event..).collect();
+        // Nothing is delivered after a parsing error, whether it was reported
+        // by an earlier call or is among these events.
+        let mut errored = self.parsing_error
+            && !events
+                .iter()
+                .any(|(event, _)| matches!(event, MessageParserEvent::ParsingError(_, _)));
+        for (event, raw) in events {
+            match event {
+                MessageParserEvent::ObjectMessage(_, _, _) if errored => {}
+                MessageParserEvent::ParsingError(_, _) => {
+                    errored = true;
+                    self.parser_events.push_back((event, raw));
+                }
+                MessageParserEvent::ObjectMessage(_, payload, false) => {
+                    let partial_object = self.partial_object.get_or_insert_with(BytesMut::new);
+                    partial_object.put(payload);
+                    if partial_object.len() > self.max_object_size {
+                        errored = true;
+                        self.partial_object = None;
+                        self.parse_error(
+                            ErrorCode::InternalError,
+                            "Object exceeds maximum buffered size".to_string(),
+                        );
+                    }
+                }
+                MessageParserEvent::ObjectMessage(object_header, payload, true) => {
+                    let payload = if let Some(mut partial_object) = self.partial_object.take() {
+                        partial_object.put(payload);
+                        partial_object.freeze()
+                    } else {
+                        payload
+                    };
+                    if payload.len() > self.max_object_size {
+                        errored = true;
+                        self.parse_error(
+                            ErrorCode::InternalError,
+                            "Object exceeds maximum buffered size".to_string(),
+                        );
+                        continue;
+                    }
+                    self.parser_events.push_back((
+                        MessageParserEvent::ObjectMessage(object_header, payload, true),
+                        None,
+                    ));
+                }
+                event => self.parser_events.push_back((event, raw)),
+            }
+        }
+    }
+
     fn parse_error(&mut self, error_code: ErrorCode, error_reason: String) {
         if self.parsing_error {
             return; // Don't send multiple parse errors.
         }
         self.no_more_data = true;
         self.parsing_error = true;
-        self.parser_events
-            .push_back(MessageParserEvent::ParsingError(error_code, error_reason));
+        self.parser_events.push_back((
+            MessageParserEvent::ParsingError(error_code, error_reason),
+            None,
+        ));
     }
 
     // Simplify understanding of state.
@@ -1,12 +1,17 @@
-use crate::message::message_parser::{ErrorCode, MessageParser, MessageParserEvent};
+use crate::message::message_framer::MessageFramer;
+use crate::message::message_parser::{
+    fuzz_control_parser, ErrorCode, MessageParser, MessageParserEvent, SetupViolation,
+};
 use crate::message::message_test::{
     create_test_message, MessageStructuredData, TestMessageBase, TestObjectDatagramMessage,
     TestObjectStreamMessage, TestStreamHeaderGroupMessage, TestStreamHeaderTrackMessage,
     TestStreamMiddlerGroupMessage, TestStreamMiddlerTrackMessage, TestSubscribeDoneMessage,
     TestSubscribeOkMessage,
 };
-use crate::message::object::ObjectHeader;
-use crate::message::{ControlMessage, FilterType, MessageType, MAX_MESSSAGE_HEADER_SIZE};
+use crate::message::object::{ObjectHeader, ObjectStatus};
+use crate::message::{
+    ControlMessage, FilterType, MessageType, Perspective, MAX_MESSSAGE_HEADER_SIZE,
+};
 use crate::{Error, Result, Serializer};
 use bytes::Bytes;
 use rstest::rstest;
@@ -246,7 +251,7 @@ fn test_one_message_with_long_varints(params: (MessageType, bool)) -> Result<()>
         );
     }
     assert!(
-        !tester.visitor.parsing_error.is_some(),
+        tester.visitor.parsing_error.is_none(),
         "message type {:?}",
         tester.message_type
     );
@@ -335,7 +340,7 @@ fn test_two_part_message(params: (MessageType, bool)) -> Result<()> {
         tester.message_type
     );
     assert!(
-        !tester.visitor.parsing_error.is_some(),
+        tester.visitor.parsing_error.is_none(),
         "message type {:?}",
         tester.message_type
     );
@@ -433,7 +438,7 @@ fn test_one_byte_at_atime(params: (MessageType, bool)) -> Result<()> {
         tester.message_type
     );
     assert!(
-        !tester.visitor.parsing_error.is_some(),
+        tester.visitor.parsing_error.is_none(),
         "message type {:?}",
         tester.message_type
     );
@@ -537,7 +542,7 @@ fn test_one_byte_at_a_time_longer_varints(params: (MessageType, bool)) -> Result
         tester.message_type
     );
     assert!(
-        !tester.visitor.parsing_error.is_some(),
+        tester.visitor.parsing_error.is_none(),
         "message type {:?}",
         tester.message_type
     );
@@ -701,7 +706,7 @@ fn test_object_stream_separate_fin() -> Result<()> {
     assert!(tester.visitor.object_payload.is_some());
     assert_eq!(tester.visitor.object_payload, Some(Bytes::from_static(b"")));
     assert!(tester.visitor.end_of_message);
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
     Ok(())
 }
 
@@ -755,7 +760,7 @@ fn test_three_part_object() -> Result<()> {
         tester.visitor.object_payload,
         Some(Bytes::from_static(b"deadbeef"))
     );
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
 
     Ok(())
 }
@@ -806,7 +811,7 @@ fn test_three_part_object_first_incomplete() -> Result<()> {
         tester.visitor.object_payload,
         Some(Bytes::from_static(b"bar"))
     );
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
 
     Ok(())
 }
@@ -830,7 +835,7 @@ fn test_stream_header_group_follow_on() -> Result<()> {
         tester.visitor.object_payload,
         Some(Bytes::from_static(b"foo"))
     );
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
     // second part
     let message2 = TestStreamMiddlerGroupMessage::new();
     parser.process_data(&mut message2.packet_sample(), false);
@@ -846,7 +851,7 @@ fn test_stream_header_group_follow_on() -> Result<()> {
         tester.visitor.object_payload,
         Some(Bytes::from_static(b"bar"))
     );
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
 
     Ok(())
 }
@@ -870,7 +875,7 @@ fn test_stream_header_track_follow_on() -> Result<()> {
         tester.visitor.object_payload,
         Some(Bytes::from_static(b"foo"))
     );
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
     // second part
     let message2 = TestStreamMiddlerTrackMessage::new();
     parser.process_data(&mut message2.packet_sample(), false);
@@ -886,7 +891,7 @@ fn test_stream_header_track_follow_on() -> Result<()> {
         tester.visitor.object_payload,
         Some(Bytes::from_static(b"bar"))
     );
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
 
     Ok(())
 }
@@ -909,7 +914,7 @@ fn test_client_setup_role_is_invalid() -> Result<()> {
     assert!(tester.visitor.parsing_error.is_some());
     assert_eq!(
         tester.visitor.parsing_error,
-        Some("Invalid ROLE parameter".to_string())
+        Some("ROLE parameter value 4 is out of range".to_string())
     );
     assert_eq!(
         tester.visitor.parsing_error_code,
@@ -936,7 +941,7 @@ fn test_server_setup_role_is_invalid() -> Result<()> {
     assert!(tester.visitor.parsing_error.is_some());
     assert_eq!(
         tester.visitor.parsing_error,
-        Some("Invalid ROLE parameter".to_string())
+        Some("ROLE parameter value 4 is out of range".to_string())
     );
     assert_eq!(
         tester.visitor.parsing_error_code,
@@ -1006,7 +1011,7 @@ fn test_client_setup_role_is_missing() -> Result<()> {
 fn test_server_setup_role_is_missing() -> Result<()> {
     let mut tester = TestMessageSpecific::new();
     let mut parser = MessageParser::new(K_RAW_QUIC);
-    let setup = vec![
+    let setup = [
         0x40, 0x41, 0x01, 0x00, // 1 param
     ];
     parser.process_data(&mut &setup[..], false);
@@ -1046,7 +1051,36 @@ fn test_setup_role_varint_length_is_wrong() -> Result<()> {
     assert!(tester.visitor.parsing_error.is_some());
     assert_eq!(
         tester.visitor.parsing_error,
-        Some("Parameter length does not match varint encoding".to_string())
+        Some("ROLE parameter length does not match varint encoding".to_string())
+    );
+
+    assert_eq!(
+        tester.visitor.parsing_error_code,
+        ErrorCode::ParameterLengthMismatch
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_server_setup_role_varint_length_is_wrong() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let setup = [
+        0x40, 0x41, // type
+        0x01, // version
+        0x01, // 1 parameter
+        0x00, 0x02, 0x03, 0x00, // role = PubSub as a 1-byte varint, but length is 2
+    ];
+    parser.process_data(&mut &setup[..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert_eq!(tester.visitor.messages_received, 0);
+    assert!(tester.visitor.parsing_error.is_some());
+    assert_eq!(
+        tester.visitor.parsing_error,
+        Some("ROLE parameter length does not match varint encoding".to_string())
     );
 
     assert_eq!(
@@ -1074,7 +1108,7 @@ fn test_setup_path_from_server() -> Result<()> {
     assert!(tester.visitor.parsing_error.is_some());
     assert_eq!(
         tester.visitor.parsing_error,
-        Some("PATH parameter in SERVER_SETUP".to_string())
+        Some("PATH parameter is not allowed in SERVER_SETUP".to_string())
     );
     assert_eq!(
         tester.visitor.parsing_error_code,
@@ -1385,6 +1419,56 @@ fn test_invalid_object_status() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_object_status_five() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let object_stream = vec![
+        0x00, 0x03, 0x04, 0x05, 0x06, 0x07, 0x05, // status = 5
+        0x66, 0x6f, 0x6f, // payload = "foo"
+    ];
+    parser.process_data(&mut &object_stream[..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert!(tester.visitor.parsing_error.is_some());
+    assert_eq!(
+        tester.visitor.parsing_error,
+        Some("Invalid object status".to_string())
+    );
+    assert_eq!(
+        tester.visitor.parsing_error_code,
+        ErrorCode::ProtocolViolation
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_object_status_ninety_nine() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let object_stream = vec![
+        0x00, 0x03, 0x04, 0x05, 0x06, 0x07, 0x40, 0x63, // status = 99
+        0x66, 0x6f, 0x6f, // payload = "foo"
+    ];
+    parser.process_data(&mut &object_stream[..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert!(tester.visitor.parsing_error.is_some());
+    assert_eq!(
+        tester.visitor.parsing_error,
+        Some("Invalid object status".to_string())
+    );
+    assert_eq!(
+        tester.visitor.parsing_error_code,
+        ErrorCode::ProtocolViolation
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_setup2kb() -> Result<()> {
     let mut tester = TestMessageSpecific::new();
@@ -1456,14 +1540,9 @@ fn test_latest_group() -> Result<()> {
     {
         message
     } else {
-        assert!(false);
         return Err(Error::ErrInvalidMessageType(0));
     };
-    if let FilterType::LatestGroup = message.filter_type {
-        assert!(true);
-    } else {
-        assert!(false);
-    }
+    assert!(matches!(message.filter_type, FilterType::LatestGroup));
 
     Ok(())
 }
@@ -1485,20 +1564,15 @@ fn test_latest_object() -> Result<()> {
         tester.visitor.handle_event(event);
     }
     assert_eq!(tester.visitor.messages_received, 1);
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
     let message = if let Some(MessageStructuredData::Control(ControlMessage::Subscribe(message))) =
         tester.visitor.last_message
     {
         message
     } else {
-        assert!(false);
         return Err(Error::ErrInvalidMessageType(0));
     };
-    if let FilterType::LatestObject = message.filter_type {
-        assert!(true);
-    } else {
-        assert!(false);
-    }
+    assert!(matches!(message.filter_type, FilterType::LatestObject));
 
     Ok(())
 }
@@ -1522,20 +1596,19 @@ fn test_absolute_start() -> Result<()> {
         tester.visitor.handle_event(event);
     }
     assert_eq!(tester.visitor.messages_received, 1);
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
     let message = if let Some(MessageStructuredData::Control(ControlMessage::Subscribe(message))) =
         tester.visitor.last_message
     {
         message
     } else {
-        assert!(false);
         return Err(Error::ErrInvalidMessageType(0));
     };
     if let FilterType::AbsoluteStart(start) = message.filter_type {
         assert_eq!(start.group_id, 4);
         assert_eq!(start.object_id, 1);
     } else {
-        assert!(false);
+        panic!("unexpected filter type");
     }
 
     Ok(())
@@ -1562,13 +1635,12 @@ fn test_absolute_range_explicit_end_object() -> Result<()> {
         tester.visitor.handle_event(event);
     }
     assert_eq!(tester.visitor.messages_received, 1);
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
     let message = if let Some(MessageStructuredData::Control(ControlMessage::Subscribe(message))) =
         tester.visitor.last_message
     {
         message
     } else {
-        assert!(false);
         return Err(Error::ErrInvalidMessageType(0));
     };
     if let FilterType::AbsoluteRange(start, end) = message.filter_type {
@@ -1577,7 +1649,7 @@ fn test_absolute_range_explicit_end_object() -> Result<()> {
         assert_eq!(end.group_id, 7);
         assert_eq!(end.object_id, 2);
     } else {
-        assert!(false);
+        panic!("unexpected filter type");
     }
 
     Ok(())
@@ -1604,13 +1676,12 @@ fn test_absolute_range_whole_end_group() -> Result<()> {
         tester.visitor.handle_event(event);
     }
     assert_eq!(tester.visitor.messages_received, 1);
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
     let message = if let Some(MessageStructuredData::Control(ControlMessage::Subscribe(message))) =
         tester.visitor.last_message
     {
         message
     } else {
-        assert!(false);
         return Err(Error::ErrInvalidMessageType(0));
     };
     if let FilterType::AbsoluteRange(start, end) = message.filter_type {
@@ -1619,7 +1690,7 @@ fn test_absolute_range_whole_end_group() -> Result<()> {
         assert_eq!(end.group_id, 7);
         assert_eq!(end.object_id, u64::MAX);
     } else {
-        assert!(false);
+        panic!("unexpected filter type");
     }
 
     Ok(())
@@ -1683,7 +1754,7 @@ fn test_absolute_range_exactly_one_object() -> Result<()> {
 fn test_subscribe_update_exactly_one_object() -> Result<()> {
     let mut tester = TestMessageSpecific::new();
     let mut parser = MessageParser::new(K_RAW_QUIC);
-    let subscribe_update = vec![
+    let subscribe_update = [
         0x02, 0x02, 0x03, 0x01, 0x04, 0x07, // start and end sequences
         0x00, // No parameters
     ];
@@ -1861,9 +1932,9 @@ fn test_all_messages_together() -> Result<()> {
         let last_message = tester.visitor.last_message.as_ref().unwrap();
         assert!(prev_message.equal_field_values(last_message));
     } else {
-        assert!(false);
+        panic!("no previous message");
     }
-    assert!(!tester.visitor.parsing_error.is_some());
+    assert!(tester.visitor.parsing_error.is_none());
 
     Ok(())
 }
@@ -1908,7 +1979,7 @@ fn test_truncated_datagram() -> Result<()> {
 
 #[test]
 fn test_very_truncated_datagram() -> Result<()> {
-    let message = vec![0x40];
+    let message = [0x40];
     let result = MessageParser::process_datagram(&mut &message[..]);
     assert!(result.is_err());
     assert_eq!(Err(Error::ErrUnexpectedEnd), result);
@@ -1955,3 +2026,677 @@ fn test_subscribe_done_invalid_content_exists() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_buffered_len_partial_message() -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    assert_eq!(parser.buffered_len(), 0);
+    assert!(!parser.has_pending());
+
+    let message = create_test_message(MessageType::Subscribe, K_RAW_QUIC);
+    let packet = message.packet_sample();
+    let half = packet.len() / 2;
+    parser.process_data(&mut &packet[..half], false);
+    assert!(parser.poll_event().is_none());
+    assert_eq!(parser.buffered_len(), half);
+    assert!(parser.has_pending());
+
+    parser.process_data(&mut &packet[half..], false);
+    assert!(matches!(
+        parser.poll_event(),
+        Some(MessageParserEvent::ControlMessage(
+            ControlMessage::Subscribe(_)
+        ))
+    ));
+    assert_eq!(parser.buffered_len(), 0);
+    assert!(!parser.has_pending());
+    Ok(())
+}
+
+#[test]
+fn test_fuzz_control_parser_corpus() -> Result<()> {
+    let corpus: [&[u8]; 8] = [
+        // Truncated varints.
+        &[0x40],
+        &[0x03, 0x80, 0x00],
+        &[0xc0, 0x00, 0x00, 0x00],
+        // SUBSCRIBE cut off inside track_name.
+        &[0x03, 0x01, 0x02, 0x03, 0x66, 0x6f, 0x6f, 0x04, 0x61],
+        // ANNOUNCE whose parameter length exceeds the message.
+        &[0x06, 0x03, 0x66, 0x6f, 0x6f, 0x01, 0x02, 0x10, 0x62],
+        // Unknown message type.
+        &[0x3f, 0x00, 0x00],
+        // OBJECT_STREAM with invalid status followed by payload.
+        &[0x00, 0x03, 0x04, 0x05, 0x06, 0x07, 0x06, 0x66, 0x6f, 0x6f],
+        // Group stream header with a follow-on object that is truncated.
+        &[
+            0x40, 0x51, 0x03, 0x04, 0x05, 0x07, 0x06, 0x03, 0x66, 0x6f, 0x6f, 0x07, 0x05,
+        ],
+    ];
+    for data in corpus {
+        for uses_web_transport in [false, true] {
+            fuzz_control_parser(data, uses_web_transport);
+        }
+    }
+    for message_type in [
+        MessageType::ClientSetup,
+        MessageType::Subscribe,
+        MessageType::StreamHeaderTrack,
+    ] {
+        let message = create_test_message(message_type, K_RAW_QUIC);
+        fuzz_control_parser(message.packet_sample(), K_RAW_QUIC);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_client_setup_too_many_versions() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let setup = [
+        0x40, 0x40, // type
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // 2^62 - 1 versions
+        0x01, // only one version present
+    ];
+    parser.process_data(&mut &setup[..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert_eq!(
+        tester.visitor.parsing_error,
+        Some("Too many supported versions in CLIENT_SETUP".to_string())
+    );
+    assert_eq!(
+        tester.visitor.parsing_error_code,
+        ErrorCode::ProtocolViolation
+    );
+    Ok(())
+}
+
+#[test]
+fn test_subscribe_too_many_parameters() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let subscribe = [
+        0x03, 0x01, 0x02, // id and alias
+        0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+        0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
+        0x01, // filter_type = LatestGroup
+        0x80, 0x01, 0x00, 0x00, // 65536 parameters
+    ];
+    parser.process_data(&mut &subscribe[..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert_eq!(
+        tester.visitor.parsing_error,
+        Some("Too many parameters in SUBSCRIBE".to_string())
+    );
+    assert_eq!(
+        tester.visitor.parsing_error_code,
+        ErrorCode::ProtocolViolation
+    );
+    Ok(())
+}
+
+#[test]
+fn test_subscribe_update_too_many_parameters() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let subscribe_update = [
+        0x02, 0x02, 0x03, 0x01, 0x05, 0x06, // start and end sequences
+        0x80, 0x01, 0x00, 0x00, // 65536 parameters
+    ];
+    parser.process_data(&mut &subscribe_update[..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert_eq!(
+        tester.visitor.parsing_error,
+        Some("Too many parameters in SUBSCRIBE_UPDATE".to_string())
+    );
+    assert_eq!(
+        tester.visitor.parsing_error_code,
+        ErrorCode::ProtocolViolation
+    );
+    Ok(())
+}
+
+#[test]
+fn test_fin_at_message_boundary() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let message = create_test_message(MessageType::GoAway, K_RAW_QUIC);
+    parser.process_data(&mut message.packet_sample(), true);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert_eq!(tester.visitor.messages_received, 1);
+    assert!(tester.visitor.parsing_error.is_none());
+    assert!(!parser.has_pending());
+    Ok(())
+}
+
+#[test]
+fn test_parse_control_message() -> Result<()> {
+    let message = create_test_message(MessageType::Subscribe, K_RAW_QUIC);
+    let packet = message.packet_sample();
+    let (control_message, len) =
+        MessageParser::parse_control_message(&mut &packet[..], K_RAW_QUIC)?;
+    assert_eq!(len, packet.len());
+    assert!(message.equal_field_values(&MessageStructuredData::Control(control_message)));
+
+    let truncated = &packet[..packet.len() - 1];
+    assert!(MessageParser::parse_control_message(&mut &truncated[..], K_RAW_QUIC).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_reserialize_announce() -> Result<()> {
+    let framer = MessageFramer::new(Perspective::Server);
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let message = create_test_message(MessageType::Announce, K_RAW_QUIC);
+    parser.process_data(&mut message.packet_sample(), false);
+    let event = if let Some(event) = parser.poll_event() {
+        event
+    } else {
+        return Err(Error::ErrInvalidMessageType(MessageType::Announce as u64));
+    };
+    assert_eq!(&event.reserialize(&framer)?[..], message.packet_sample());
+
+    let error = MessageParserEvent::ParsingError(ErrorCode::ProtocolViolation, "bad".to_string());
+    assert!(matches!(
+        error.reserialize(&framer),
+        Err(Error::ErrParseError(ErrorCode::ProtocolViolation, _))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_group_stream_payload_in_chunks() -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let header = [
+        0x40, 0x51, // type = StreamHeaderGroup
+        0x03, 0x04, 0x05, 0x07, // subscribe_id, track_alias, group_id, send_order
+        0x06, 0x0a, // object_id = 6, payload_length = 10
+    ];
+    parser.process_data(&mut &header[..], false);
+    // The header is delivered as soon as it is complete, ahead of any payload.
+    if let Some(MessageParserEvent::ObjectMessage(object_header, data, false)) = parser.poll_event()
+    {
+        assert_eq!(object_header.object_id, 6);
+        assert!(data.is_empty());
+    } else {
+        return Err(Error::ErrInvalidMessageType(
+            MessageType::StreamHeaderGroup as u64,
+        ));
+    }
+    assert!(parser.poll_event().is_none());
+
+    let payload = b"0123456789";
+    let mut received = vec![];
+    for (i, chunk) in payload.chunks(3).enumerate() {
+        parser.process_data(&mut &chunk[..], false);
+        if let Some(MessageParserEvent::ObjectMessage(object_header, data, end_of_message)) =
+            parser.poll_event()
+        {
+            assert_eq!(object_header.object_payload_length, Some(10));
+            assert_eq!(end_of_message, i == 3, "chunk {}", i);
+            received.extend_from_slice(&data);
+        } else {
+            return Err(Error::ErrInvalidMessageType(
+                MessageType::StreamHeaderGroup as u64,
+            ));
+        }
+        assert!(parser.poll_event().is_none());
+    }
+    assert_eq!(&received[..], &payload[..]);
+    Ok(())
+}
+
+#[test]
+fn test_group_stream_end_of_group_then_normal_object() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let stream = [
+        0x40, 0x51, // type = StreamHeaderGroup
+        0x03, 0x04, 0x05, 0x07, // subscribe_id, track_alias, group_id, send_order
+        0x06, 0x00, 0x03, // object_id = 6, payload_length = 0, status = EndOfGroup
+        0x07, 0x03, 0x66, 0x6f, 0x6f, // object_id = 7, payload = "foo"
+    ];
+    parser.process_data(&mut &stream[..], false);
+
+    if let Some(MessageParserEvent::ObjectMessage(object_header, payload, true)) =
+        parser.poll_event()
+    {
+        assert_eq!(object_header.object_id, 6);
+        assert_eq!(object_header.object_status, ObjectStatus::EndOfGroup);
+        assert!(payload.is_empty());
+    } else {
+        return Err(Error::ErrInvalidMessageType(
+            MessageType::StreamHeaderGroup as u64,
+        ));
+    }
+    if let Some(MessageParserEvent::ObjectMessage(object_header, payload, true)) =
+        parser.poll_event()
+    {
+        assert_eq!(object_header.object_id, 7);
+        assert_eq!(object_header.object_status, ObjectStatus::Normal);
+        assert_eq!(&payload[..], b"foo");
+    } else {
+        return Err(Error::ErrInvalidMessageType(
+            MessageType::StreamHeaderGroup as u64,
+        ));
+    }
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert!(tester.visitor.parsing_error.is_none());
+    Ok(())
+}
+
+#[rstest(deliver_partial_objects, case(true), case(false))]
+fn test_deliver_partial_objects(deliver_partial_objects: bool) -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    parser.set_deliver_partial_objects(deliver_partial_objects);
+    let header = [
+        0x40, 0x51, // type = StreamHeaderGroup
+        0x03, 0x04, 0x05, 0x07, // subscribe_id, track_alias, group_id, send_order
+        0x06, 0x06, // object_id = 6, payload_length = 6
+    ];
+    parser.process_data(&mut &header[..], false);
+    parser.process_data(&mut &b"foo"[..], false);
+    parser.process_data(&mut &b"bar"[..], false);
+
+    let mut chunks = vec![];
+    while let Some(event) = parser.poll_event() {
+        if let MessageParserEvent::ObjectMessage(object_header, payload, end_of_message) = event {
+            assert_eq!(object_header.object_id, 6);
+            chunks.push((payload, end_of_message));
+        } else {
+            return Err(Error::ErrInvalidMessageType(
+                MessageType::StreamHeaderGroup as u64,
+            ));
+        }
+    }
+    if deliver_partial_objects {
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[..2]
+            .iter()
+            .all(|(_, end_of_message)| !end_of_message));
+    } else {
+        assert_eq!(chunks.len(), 1);
+    }
+    let (last, end_of_message) = chunks.last().unwrap();
+    assert!(*end_of_message);
+    if !deliver_partial_objects {
+        assert_eq!(&last[..], b"foobar");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_buffered_object_too_large() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    parser.set_deliver_partial_objects(false);
+    parser.set_max_object_size(4);
+    let header = [
+        0x40, 0x51, // type = StreamHeaderGroup
+        0x03, 0x04, 0x05, 0x07, // subscribe_id, track_alias, group_id, send_order
+        0x06, 0x06, // object_id = 6, payload_length = 6
+    ];
+    parser.process_data(&mut &header[..], false);
+    parser.process_data(&mut &b"foo"[..], false);
+    assert!(parser.poll_event().is_none());
+    parser.process_data(&mut &b"bar"[..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert_eq!(tester.visitor.messages_received, 0);
+    assert_eq!(
+        tester.visitor.parsing_error,
+        Some("Object exceeds maximum buffered size".to_string())
+    );
+    assert_eq!(tester.visitor.parsing_error_code, ErrorCode::InternalError);
+
+    // A complete small object after the error is not delivered.
+    parser.process_data(&mut &[0x07, 0x01, 0x78][..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert_eq!(tester.visitor.messages_received, 0);
+    Ok(())
+}
+
+#[test]
+fn test_setup_violation_sub_reason() -> Result<()> {
+    let with_path = [
+        0x40, 0x40, 0x02, 0x01, 0x02, // versions = 1, 2
+        0x02, // 2 params
+        0x00, 0x01, 0x03, // role = PubSub
+        0x01, 0x03, 0x66, 0x6f, 0x6f, // path = "foo"
+    ];
+    let without_path = [
+        0x40, 0x40, 0x02, 0x01, 0x02, // versions = 1, 2
+        0x01, // 1 param
+        0x00, 0x01, 0x03, // role = PubSub
+    ];
+    assert_eq!(
+        MessageParser::parse_control_message(&mut &with_path[..], K_WEB_TRANS).err(),
+        Some(Error::ErrSetupViolation(
+            SetupViolation::PathOverWebTransport
+        ))
+    );
+    assert_eq!(
+        MessageParser::parse_control_message(&mut &without_path[..], K_RAW_QUIC).err(),
+        Some(Error::ErrSetupViolation(
+            SetupViolation::MissingPathOverQuic
+        ))
+    );
+    assert!(MessageParser::parse_control_message(&mut &with_path[..], K_RAW_QUIC).is_ok());
+    assert!(MessageParser::parse_control_message(&mut &without_path[..], K_WEB_TRANS).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_track_stream_spanning_groups() -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let stream = [
+        0x40, 0x50, // type = StreamHeaderTrack
+        0x03, 0x04, 0x07, // subscribe_id, track_alias, send_order
+        0x05, 0x06, 0x03, 0x66, 0x6f, 0x6f, // group 5, object 6, payload = "foo"
+        0x06, 0x00, 0x03, 0x62, 0x61, 0x72, // group 6, object 0, payload = "bar"
+    ];
+    parser.process_data(&mut &stream[..], false);
+
+    let mut locations = vec![];
+    while let Some(event) = parser.poll_event() {
+        if let MessageParserEvent::ObjectMessage(object_header, _, true) = event {
+            assert_eq!(object_header.subscribe_id, 3);
+            assert_eq!(object_header.track_alias, 4);
+            assert_eq!(object_header.object_send_order, 7);
+            locations.push((object_header.group_id, object_header.object_id));
+        } else {
+            return Err(Error::ErrInvalidMessageType(
+                MessageType::StreamHeaderTrack as u64,
+            ));
+        }
+    }
+    assert_eq!(locations, vec![(5, 6), (6, 0)]);
+    Ok(())
+}
+
+#[test]
+fn test_group_stream_keeps_group() -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let stream = [
+        0x40, 0x51, // type = StreamHeaderGroup
+        0x03, 0x04, 0x05, 0x07, // subscribe_id, track_alias, group_id, send_order
+        0x06, 0x03, 0x66, 0x6f, 0x6f, // object 6, payload = "foo"
+        0x07, 0x03, 0x62, 0x61, 0x72, // object 7, payload = "bar"
+    ];
+    parser.process_data(&mut &stream[..], false);
+
+    let mut locations = vec![];
+    while let Some(event) = parser.poll_event() {
+        if let MessageParserEvent::ObjectMessage(object_header, _, true) = event {
+            locations.push((object_header.group_id, object_header.object_id));
+        } else {
+            return Err(Error::ErrInvalidMessageType(
+                MessageType::StreamHeaderGroup as u64,
+            ));
+        }
+    }
+    assert_eq!(locations, vec![(5, 6), (5, 7)]);
+    Ok(())
+}
+
+#[test]
+fn test_parser_state_after_fin() -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let message = create_test_message(MessageType::UnSubscribe, K_RAW_QUIC);
+    let packet = message.packet_sample();
+    parser.process_data(&mut &packet[..1], false);
+    assert_eq!(parser.buffered_len(), 1);
+    assert!(!parser.no_more_data());
+
+    parser.process_data(&mut &packet[1..], true);
+    assert_eq!(parser.buffered_len(), 0);
+    assert!(parser.no_more_data());
+    assert!(!parser.parsing_error());
+
+    // Data after FIN is an error.
+    parser.process_data(&mut &packet[..], false);
+    assert!(parser.parsing_error());
+    Ok(())
+}
+
+#[rstest(reject_empty_auth_info, case(true), case(false))]
+fn test_empty_auth_info(reject_empty_auth_info: bool) -> Result<()> {
+    let empty = [
+        0x06, 0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+        0x01, // 1 parameter
+        0x02, 0x00, // authorization_info = ""
+    ];
+    let non_empty = [
+        0x06, 0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+        0x01, // 1 parameter
+        0x02, 0x03, 0x62, 0x61, 0x72, // authorization_info = "bar"
+    ];
+    for (packet, expected) in [(&empty[..], ""), (&non_empty[..], "bar")] {
+        let mut parser = MessageParser::new(K_RAW_QUIC);
+        parser.set_reject_empty_auth_info(reject_empty_auth_info);
+        parser.process_data(&mut &packet[..], false);
+        match parser.poll_event() {
+            Some(MessageParserEvent::ControlMessage(ControlMessage::Announce(announce))) => {
+                assert!(!(reject_empty_auth_info && expected.is_empty()));
+                // An empty value is present, not absent.
+                assert_eq!(announce.authorization_info, Some(expected.to_string()));
+            }
+            Some(MessageParserEvent::ParsingError(code, reason)) => {
+                assert!(reject_empty_auth_info && expected.is_empty());
+                assert_eq!(code, ErrorCode::ProtocolViolation);
+                assert_eq!(reason, "Empty AUTHORIZATION_INFO parameter");
+            }
+            _ => return Err(Error::ErrInvalidMessageType(MessageType::Announce as u64)),
+        }
+    }
+    Ok(())
+}
+
+#[rstest(validate_go_away_uri, case(true), case(false))]
+fn test_go_away_uri_validation(validate_go_away_uri: bool) -> Result<()> {
+    let valid = b"https://example.com/moq";
+    let garbage = b"\x01\x02\nfoo\x7f";
+    for (uri, plausible) in [(&valid[..], true), (&garbage[..], false)] {
+        let mut packet = vec![0x10, uri.len() as u8];
+        packet.extend_from_slice(uri);
+        let mut parser = MessageParser::new(K_RAW_QUIC);
+        parser.set_validate_go_away_uri(validate_go_away_uri);
+        parser.process_data(&mut &packet[..], false);
+        match parser.poll_event() {
+            Some(MessageParserEvent::ControlMessage(ControlMessage::GoAway(go_away))) => {
+                assert!(plausible || !validate_go_away_uri);
+                assert_eq!(go_away.new_session_uri.as_bytes(), uri);
+            }
+            Some(MessageParserEvent::ParsingError(code, reason)) => {
+                assert!(validate_go_away_uri && !plausible);
+                assert_eq!(code, ErrorCode::ProtocolViolation);
+                assert_eq!(reason, "GOAWAY new_session_uri is not a valid URI");
+            }
+            _ => return Err(Error::ErrInvalidMessageType(MessageType::GoAway as u64)),
+        }
+    }
+    Ok(())
+}
+
+#[rstest(separate_fin, case(true), case(false))]
+fn test_max_events(separate_fin: bool) -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    parser.set_max_events(2);
+    let message = create_test_message(MessageType::UnSubscribe, K_RAW_QUIC);
+    let mut packets = vec![];
+    for _ in 0..5 {
+        packets.extend_from_slice(message.packet_sample());
+    }
+    parser.process_data(&mut &packets[..], !separate_fin);
+    if separate_fin {
+        // The buffered messages are complete; they are only held back.
+        parser.process_data(&mut &[][..], true);
+    }
+    assert!(parser.has_pending());
+    assert!(!parser.parsing_error());
+
+    let mut received = 0;
+    loop {
+        let mut drained = 0;
+        while let Some(event) = parser.poll_event() {
+            if let MessageParserEvent::ControlMessage(_) = event {
+                drained += 1;
+            } else {
+                return Err(Error::ErrInvalidMessageType(
+                    MessageType::UnSubscribe as u64,
+                ));
+            }
+        }
+        assert!(drained <= 2);
+        received += drained;
+        if !parser.has_pending() {
+            break;
+        }
+        parser.resume();
+    }
+    assert_eq!(received, 5);
+    assert!(!parser.parsing_error());
+    Ok(())
+}
+
+#[test]
+fn test_max_events_fin_after_incomplete() -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    parser.set_max_events(1);
+    let message = create_test_message(MessageType::UnSubscribe, K_RAW_QUIC);
+    let mut packets = vec![];
+    packets.extend_from_slice(message.packet_sample());
+    packets.extend_from_slice(message.packet_sample());
+    packets.push(message.packet_sample()[0]);
+    parser.process_data(&mut &packets[..], false);
+    parser.process_data(&mut &[][..], true);
+    assert!(!parser.parsing_error());
+
+    assert!(matches!(
+        parser.poll_event(),
+        Some(MessageParserEvent::ControlMessage(_))
+    ));
+    parser.resume();
+    assert!(matches!(
+        parser.poll_event(),
+        Some(MessageParserEvent::ControlMessage(_))
+    ));
+    // The truncated last message is reported once parsing reaches it.
+    parser.resume();
+    assert!(matches!(
+        parser.poll_event(),
+        Some(MessageParserEvent::ParsingError(
+            ErrorCode::ProtocolViolation,
+            _
+        ))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_capture_raw() -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    parser.set_capture_raw(true);
+    let subscribe = create_test_message(MessageType::Subscribe, K_RAW_QUIC);
+    let unsubscribe = create_test_message(MessageType::UnSubscribe, K_RAW_QUIC);
+    let mut packets = subscribe.packet_sample().to_vec();
+    packets.extend_from_slice(unsubscribe.packet_sample());
+    // Split mid-message so the first message is reassembled from two buffers.
+    parser.process_data(&mut &packets[..3], false);
+    parser.process_data(&mut &packets[3..], false);
+
+    for expected in [subscribe.packet_sample(), unsubscribe.packet_sample()] {
+        if let Some((MessageParserEvent::ControlMessage(_), Some(raw))) =
+            parser.poll_event_with_raw()
+        {
+            assert_eq!(&raw[..], expected);
+        } else {
+            return Err(Error::ErrInvalidMessageType(MessageType::Subscribe as u64));
+        }
+    }
+    assert!(parser.poll_event_with_raw().is_none());
+
+    // Without capture_raw, no bytes are returned.
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    parser.process_data(&mut subscribe.packet_sample(), false);
+    assert!(matches!(
+        parser.poll_event_with_raw(),
+        Some((MessageParserEvent::ControlMessage(_), None))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_capture_raw_toggled_with_pending_events() -> Result<()> {
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let subscribe = create_test_message(MessageType::Subscribe, K_RAW_QUIC);
+    let unsubscribe = create_test_message(MessageType::UnSubscribe, K_RAW_QUIC);
+    parser.process_data(&mut subscribe.packet_sample(), false);
+    parser.set_capture_raw(true);
+    parser.process_data(&mut unsubscribe.packet_sample(), false);
+    parser.set_capture_raw(false);
+
+    // Each event keeps the bytes captured, or not, when it was parsed.
+    assert!(matches!(
+        parser.poll_event_with_raw(),
+        Some((
+            MessageParserEvent::ControlMessage(ControlMessage::Subscribe(_)),
+            None
+        ))
+    ));
+    if let Some((MessageParserEvent::ControlMessage(ControlMessage::UnSubscribe(_)), Some(raw))) =
+        parser.poll_event_with_raw()
+    {
+        assert_eq!(&raw[..], unsubscribe.packet_sample());
+    } else {
+        return Err(Error::ErrInvalidMessageType(
+            MessageType::UnSubscribe as u64,
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_empty_reason_phrase() -> Result<()> {
+    let mut tester = TestMessageSpecific::new();
+    let mut parser = MessageParser::new(K_RAW_QUIC);
+    let packet = [
+        0x05, 0x02, // subscribe_id = 2
+        0x01, // error_code = 1
+        0x00, // reason_phrase = ""
+        0x04, // track_alias = 4
+    ];
+    parser.process_data(&mut &packet[..], false);
+    while let Some(event) = parser.poll_event() {
+        tester.visitor.handle_event(event);
+    }
+    assert_eq!(tester.visitor.messages_received, 1);
+    assert!(tester.visitor.parsing_error.is_none());
+    assert!(!parser.has_pending());
+    let subscribe_error = if let Some(MessageStructuredData::Control(
+        ControlMessage::SubscribeError(subscribe_error),
+    )) = tester.visitor.last_message
+    {
+        subscribe_error
+    } else {
+        return Err(Error::ErrInvalidMessageType(
+            MessageType::SubscribeError as u64,
+        ));
+    };
+    assert_eq!(subscribe_error.reason_phrase, "");
+    assert_eq!(subscribe_error.track_alias, 4);
+    Ok(())
+}
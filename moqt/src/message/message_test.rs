@@ -594,7 +594,7 @@ impl TestClientSetupMessage {
         };
         let mut raw_packet = vec![
             0x40, 0x40, // type
-            0x02, 0x01, 0x02, // versions
+            0x02, 0x01, 0x02, // versions 1 and 2 as 1-byte varints; real drafts take 8 bytes
             0x02, // 2 parameters
             0x00, 0x01, 0x03, // role = PubSub
             0x01, 0x03, 0x66, 0x6f, 0x6f, // path = "foo"
@@ -771,7 +771,7 @@ impl TestSubscribeMessage {
             0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
             0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
             0x03, // Filter type: Absolute Start
-            0x04, // start_group = 4 (relative previous)
+            0x04, // start_group = 4 (absolute)
             0x01, // start_object = 1 (absolute)
             // No EndGroup or EndObject
             0x01, // 1 parameter
@@ -16,7 +16,7 @@ use crate::message::track_status::TrackStatus;
 use crate::message::track_status_request::TrackStatusRequest;
 use crate::message::unannounce::UnAnnounce;
 use crate::message::unsubscribe::UnSubscribe;
-use crate::{Deserializer, Error, Result, Serializer};
+use crate::{Deserializer, Error, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
 pub mod announce;
@@ -51,6 +51,23 @@ mod message_test;
 /// message (OBJECT payloads are not buffered by the parser)
 pub const MAX_MESSSAGE_HEADER_SIZE: usize = 2048;
 
+/// The most versions a CLIENT_SETUP may list. The count is checked before any
+/// version is read, so a bogus count fails fast instead of waiting for data.
+pub const MAX_SUPPORTED_VERSIONS: usize = 64;
+/// The most parameters a message may carry. The count is checked before any
+/// parameter is read, so a bogus count fails fast instead of waiting for data.
+pub const MAX_PARAMETERS: usize = 64;
+/// The longest track namespace accepted, in bytes.
+pub const MAX_TRACK_NAMESPACE_BYTES: usize = 1024;
+
+/// The default limit on an OBJECT payload the parser will buffer when it is
+/// not delivering partial objects.
+pub const MAX_BUFFERED_OBJECT_SIZE: usize = 1 << 20;
+
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum MessageType {
     #[default]
@@ -84,6 +101,32 @@ impl MessageType {
             || *self == MessageType::StreamHeaderGroup
     }
 
+    /// The message name as written in the draft, for error reasons.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MessageType::ObjectStream => "OBJECT_STREAM",
+            MessageType::ObjectDatagram => "OBJECT_DATAGRAM",
+            MessageType::SubscribeUpdate => "SUBSCRIBE_UPDATE",
+            MessageType::Subscribe => "SUBSCRIBE",
+            MessageType::SubscribeOk => "SUBSCRIBE_OK",
+            MessageType::SubscribeError => "SUBSCRIBE_ERROR",
+            MessageType::Announce => "ANNOUNCE",
+            MessageType::AnnounceOk => "ANNOUNCE_OK",
+            MessageType::AnnounceError => "ANNOUNCE_ERROR",
+            MessageType::UnAnnounce => "UNANNOUNCE",
+            MessageType::UnSubscribe => "UNSUBSCRIBE",
+            MessageType::SubscribeDone => "SUBSCRIBE_DONE",
+            MessageType::AnnounceCancel => "ANNOUNCE_CANCEL",
+            MessageType::TrackStatusRequest => "TRACK_STATUS_REQUEST",
+            MessageType::TrackStatus => "TRACK_STATUS",
+            MessageType::GoAway => "GOAWAY",
+            MessageType::ClientSetup => "CLIENT_SETUP",
+            MessageType::ServerSetup => "SERVER_SETUP",
+            MessageType::StreamHeaderTrack => "STREAM_HEADER_TRACK",
+            MessageType::StreamHeaderGroup => "STREAM_HEADER_GROUP",
+        }
+    }
+
     pub fn is_object_without_payload_length(&self) -> bool {
         *self == MessageType::ObjectStream || *self == MessageType::ObjectDatagram
     }
@@ -97,6 +140,23 @@ impl MessageType {
             _ => Err(Error::ErrInvalidMessageType(*self as u64)),
         }
     }
+
+    /// Returns true if a unidirectional stream starting with this type carries
+    /// object data rather than control messages.
+    pub fn is_data_stream_type(value: u64) -> bool {
+        MessageType::forwarding_preference_from_wire(value).is_some()
+    }
+
+    /// Maps the first varint of a unidirectional stream to the forwarding
+    /// preference of the objects it carries, or None if it is not a data stream.
+    pub fn forwarding_preference_from_wire(value: u64) -> Option<ObjectForwardingPreference> {
+        match MessageType::try_from(value) {
+            Ok(MessageType::ObjectStream) => Some(ObjectForwardingPreference::Object),
+            Ok(MessageType::StreamHeaderTrack) => Some(ObjectForwardingPreference::Track),
+            Ok(MessageType::StreamHeaderGroup) => Some(ObjectForwardingPreference::Group),
+            _ => None,
+        }
+    }
 }
 
 impl TryFrom<u64> for MessageType {
@@ -146,7 +206,61 @@ impl Serializer for MessageType {
     }
 }
 
-#[derive(Default, Debug, Clone, Eq, PartialEq, PartialOrd, Hash)]
+impl SerializedSize for MessageType {
+    fn serialized_size(&self) -> Result<usize> {
+        (*self as u64).serialized_size()
+    }
+}
+
+/// End objects are sent as 0 when the whole end group is requested and as
+/// the object id plus one otherwise.
+pub(crate) fn decode_end_object(raw: u64) -> Option<u64> {
+    if raw == 0 {
+        None
+    } else {
+        Some(raw - 1)
+    }
+}
+
+/// Inverse of decode_end_object.
+pub(crate) fn encode_end_object(end_object: Option<u64>) -> u64 {
+    if let Some(end_object) = end_object {
+        end_object + 1
+    } else {
+        0
+    }
+}
+
+/// Reads a track namespace, which must not be empty or longer than
+/// MAX_TRACK_NAMESPACE_BYTES. The length is checked before the namespace is
+/// read, so an oversized one fails without waiting for its bytes.
+pub(crate) fn deserialize_track_namespace<R: Buf>(r: &mut R) -> Result<(String, usize)> {
+    let (size, sl) = usize::deserialize(r)?;
+    if size == 0 {
+        return Err(Error::ErrParseError(
+            ErrorCode::ProtocolViolation,
+            "Track namespace must not be empty".to_string(),
+        ));
+    }
+    if size > MAX_TRACK_NAMESPACE_BYTES {
+        return Err(Error::ErrParseError(
+            ErrorCode::ProtocolViolation,
+            "Track namespace is too long".to_string(),
+        ));
+    }
+    if r.remaining() < size {
+        return Err(Error::ErrBufferTooShort);
+    }
+    let mut buf = vec![0; size];
+    r.copy_to_slice(&mut buf);
+    Ok((String::from_utf8(buf)?, sl + size))
+}
+
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
+#[derive(Default, Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct FullTrackName {
     pub track_namespace: String,
     pub track_name: String,
@@ -159,11 +273,24 @@ impl FullTrackName {
             track_name,
         }
     }
+
+    /// Returns the namespace and the track name, or None for the name if it
+    /// is empty, i.e. the FullTrackName only identifies a namespace.
+    pub fn split_name(&self) -> (&str, Option<&str>) {
+        (
+            &self.track_namespace,
+            if self.track_name.is_empty() {
+                None
+            } else {
+                Some(&self.track_name)
+            },
+        )
+    }
 }
 
 impl Deserializer for FullTrackName {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
-        let (track_namespace, tnsl) = String::deserialize(r)?;
+        let (track_namespace, tnsl) = deserialize_track_namespace(r)?;
         let (track_name, tnl) = String::deserialize(r)?;
         Ok((
             Self {
@@ -183,7 +310,17 @@ impl Serializer for FullTrackName {
     }
 }
 
-#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Hash)]
+impl SerializedSize for FullTrackName {
+    fn serialized_size(&self) -> Result<usize> {
+        Ok(self.track_namespace.serialized_size()? + self.track_name.serialized_size()?)
+    }
+}
+
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct FullSequence {
     pub group_id: u64,
     pub object_id: u64,
@@ -203,6 +340,26 @@ impl FullSequence {
             object_id: self.object_id + 1,
         }
     }
+
+    /// Returns the (group, object) pair of this sequence.
+    pub fn as_group_object(&self) -> (u64, u64) {
+        (self.group_id, self.object_id)
+    }
+}
+
+impl From<(u64, u64)> for FullSequence {
+    fn from((group_id, object_id): (u64, u64)) -> Self {
+        Self {
+            group_id,
+            object_id,
+        }
+    }
+}
+
+impl From<FullSequence> for (u64, u64) {
+    fn from(sequence: FullSequence) -> Self {
+        sequence.as_group_object()
+    }
 }
 
 impl Deserializer for FullSequence {
@@ -227,6 +384,50 @@ impl Serializer for FullSequence {
     }
 }
 
+impl SerializedSize for FullSequence {
+    fn serialized_size(&self) -> Result<usize> {
+        Ok(self.group_id.serialized_size()? + self.object_id.serialized_size()?)
+    }
+}
+
+/// An optional sequence is encoded as a ContentExists flag, followed by the
+/// group and object only if the flag is set.
+impl Deserializer for Option<FullSequence> {
+    fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
+        let (exist, el) = bool::deserialize(r)?;
+        if exist {
+            let (sequence, sl) = FullSequence::deserialize(r)?;
+            Ok((Some(sequence), el + sl))
+        } else {
+            Ok((None, el))
+        }
+    }
+}
+
+impl Serializer for Option<FullSequence> {
+    fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
+        if let Some(sequence) = self.as_ref() {
+            Ok(true.serialize(w)? + sequence.serialize(w)?)
+        } else {
+            false.serialize(w)
+        }
+    }
+}
+
+impl SerializedSize for Option<FullSequence> {
+    fn serialized_size(&self) -> Result<usize> {
+        if let Some(sequence) = self.as_ref() {
+            Ok(true.serialized_size()? + sequence.serialized_size()?)
+        } else {
+            false.serialized_size()
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum FilterType {
     #[default]
@@ -245,6 +446,32 @@ impl FilterType {
             FilterType::AbsoluteRange(_, _) => 0x4,
         }
     }
+
+    /// Returns the inclusive start and the optional inclusive end of an
+    /// absolute filter, or None for LatestGroup/LatestObject. When the whole
+    /// end group is requested, the end object is u64::MAX.
+    pub fn object_range(&self) -> Option<(FullSequence, Option<FullSequence>)> {
+        match *self {
+            FilterType::LatestGroup | FilterType::LatestObject => None,
+            FilterType::AbsoluteStart(start) => Some((start, None)),
+            FilterType::AbsoluteRange(start, end) => Some((start, Some(end))),
+        }
+    }
+
+    // Checks an absolute range and returns its end as sent on the wire.
+    fn encode_end(start: FullSequence, mut end: FullSequence) -> Result<FullSequence> {
+        if end.group_id < start.group_id {
+            return Err(Error::ErrFrameError(
+                "End group is less than start group".to_string(),
+            ));
+        } else if end.group_id == start.group_id && end.object_id < start.object_id {
+            return Err(Error::ErrFrameError(
+                "End object comes before start object".to_string(),
+            ));
+        }
+        end.object_id = encode_end_object((end.object_id != u64::MAX).then_some(end.object_id));
+        Ok(end)
+    }
 }
 
 impl Deserializer for FilterType {
@@ -260,11 +487,7 @@ impl Deserializer for FilterType {
             0x4 => {
                 let (start, sl) = FullSequence::deserialize(r)?;
                 let (mut end, el) = FullSequence::deserialize(r)?;
-                if end.object_id == 0 {
-                    end.object_id = u64::MAX;
-                } else {
-                    end.object_id -= 1;
-                }
+                end.object_id = decode_end_object(end.object_id).unwrap_or(u64::MAX);
                 if end.group_id < start.group_id {
                     Err(Error::ErrParseError(
                         ErrorCode::ProtocolViolation,
@@ -286,32 +509,18 @@ impl Deserializer for FilterType {
 
 impl Serializer for FilterType {
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
+        let value = self.value() as u64;
         match *self {
-            FilterType::LatestGroup => 0x1u64.serialize(w),
-            FilterType::LatestObject => 0x2u64.serialize(w),
+            FilterType::LatestGroup | FilterType::LatestObject => value.serialize(w),
             FilterType::AbsoluteStart(start) => {
-                let mut l = 0x3u64.serialize(w)?;
+                let mut l = value.serialize(w)?;
                 l += start.serialize(w)?;
                 Ok(l)
             }
-            FilterType::AbsoluteRange(start, mut end) => {
-                if end.group_id < start.group_id {
-                    return Err(Error::ErrFrameError(
-                        "End group is less than start group".to_string(),
-                    ));
-                } else if end.group_id == start.group_id && end.object_id < start.object_id {
-                    return Err(Error::ErrFrameError(
-                        "End object comes before start object".to_string(),
-                    ));
-                }
-
-                let mut l = 0x4u64.serialize(w)?;
+            FilterType::AbsoluteRange(start, end) => {
+                let end = FilterType::encode_end(start, end)?;
+                let mut l = value.serialize(w)?;
                 l += start.serialize(w)?;
-                if end.object_id == u64::MAX {
-                    end.object_id = 0;
-                } else {
-                    end.object_id += 1;
-                }
                 l += end.serialize(w)?;
                 Ok(l)
             }
@@ -319,6 +528,24 @@ impl Serializer for FilterType {
     }
 }
 
+impl SerializedSize for FilterType {
+    fn serialized_size(&self) -> Result<usize> {
+        let l = (self.value() as u64).serialized_size()?;
+        match *self {
+            FilterType::LatestGroup | FilterType::LatestObject => Ok(l),
+            FilterType::AbsoluteStart(start) => Ok(l + start.serialized_size()?),
+            FilterType::AbsoluteRange(start, end) => {
+                let end = FilterType::encode_end(start, end)?;
+                Ok(l + start.serialized_size()? + end.serialized_size()?)
+            }
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 #[repr(u32)]
 pub enum Version {
@@ -352,20 +579,58 @@ impl Deserializer for Version {
     }
 }
 
-impl Serializer for Version {
-    fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
-        let value: u64 = match *self {
+impl From<Version> for u64 {
+    fn from(version: Version) -> Self {
+        match version {
             Version::Draft00 => 0xff000000,
             Version::Draft01 => 0xff000001,
             Version::Draft02 => 0xff000002,
             Version::Draft03 => 0xff000003,
             Version::Draft04 => 0xff000004,
             Version::Unsupported(value) => value as u64,
-        };
-        value.serialize(w)
+        }
     }
 }
 
+impl Serializer for Version {
+    fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
+        u64::from(*self).serialize(w)
+    }
+}
+
+impl SerializedSize for Version {
+    fn serialized_size(&self) -> Result<usize> {
+        u64::from(*self).serialized_size()
+    }
+}
+
+/// Which end of the session an endpoint is. The framer uses it to refuse
+/// messages only the other end may send.
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Perspective {
+    #[default]
+    Server,
+    Client,
+}
+
+impl Perspective {
+    pub fn is_server(&self) -> bool {
+        *self == Perspective::Server
+    }
+
+    /// The perspective of the other end of the session.
+    pub fn peer(&self) -> Perspective {
+        match *self {
+            Perspective::Server => Perspective::Client,
+            Perspective::Client => Perspective::Server,
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Role {
     Publisher = 0x1,
@@ -401,6 +666,16 @@ impl Serializer for Role {
     }
 }
 
+impl SerializedSize for Role {
+    fn serialized_size(&self) -> Result<usize> {
+        (*self as u64).serialized_size()
+    }
+}
+
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ControlMessage {
     SubscribeUpdate(SubscribeUpdate),
@@ -421,6 +696,30 @@ pub enum ControlMessage {
     ServerSetup(ServerSetup),
 }
 
+impl ControlMessage {
+    /// The type code this message is framed with.
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            ControlMessage::SubscribeUpdate(_) => MessageType::SubscribeUpdate,
+            ControlMessage::Subscribe(_) => MessageType::Subscribe,
+            ControlMessage::SubscribeOk(_) => MessageType::SubscribeOk,
+            ControlMessage::SubscribeError(_) => MessageType::SubscribeError,
+            ControlMessage::Announce(_) => MessageType::Announce,
+            ControlMessage::AnnounceOk(_) => MessageType::AnnounceOk,
+            ControlMessage::AnnounceError(_) => MessageType::AnnounceError,
+            ControlMessage::UnAnnounce(_) => MessageType::UnAnnounce,
+            ControlMessage::UnSubscribe(_) => MessageType::UnSubscribe,
+            ControlMessage::SubscribeDone(_) => MessageType::SubscribeDone,
+            ControlMessage::AnnounceCancel(_) => MessageType::AnnounceCancel,
+            ControlMessage::TrackStatusRequest(_) => MessageType::TrackStatusRequest,
+            ControlMessage::TrackStatus(_) => MessageType::TrackStatus,
+            ControlMessage::GoAway(_) => MessageType::GoAway,
+            ControlMessage::ClientSetup(_) => MessageType::ClientSetup,
+            ControlMessage::ServerSetup(_) => MessageType::ServerSetup,
+        }
+    }
+}
+
 impl Deserializer for ControlMessage {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
         let (message_type, mtl) = MessageType::deserialize(r)?;
@@ -583,3 +882,344 @@ impl Serializer for ControlMessage {
         }
     }
 }
+
+impl SerializedSize for ControlMessage {
+    fn serialized_size(&self) -> Result<usize> {
+        let l = match self {
+            ControlMessage::SubscribeUpdate(subscribe_update) => {
+                subscribe_update.serialized_size()?
+            }
+            ControlMessage::Subscribe(subscribe) => subscribe.serialized_size()?,
+            ControlMessage::SubscribeOk(subscribe_ok) => subscribe_ok.serialized_size()?,
+            ControlMessage::SubscribeError(subscribe_error) => subscribe_error.serialized_size()?,
+            ControlMessage::Announce(announce) => announce.serialized_size()?,
+            ControlMessage::AnnounceOk(announce_ok) => announce_ok.serialized_size()?,
+            ControlMessage::AnnounceError(announce_error) => announce_error.serialized_size()?,
+            ControlMessage::UnAnnounce(unannounce) => unannounce.serialized_size()?,
+            ControlMessage::UnSubscribe(unsubscribe) => unsubscribe.serialized_size()?,
+            ControlMessage::SubscribeDone(subscribe_done) => subscribe_done.serialized_size()?,
+            ControlMessage::AnnounceCancel(announce_cancel) => announce_cancel.serialized_size()?,
+            ControlMessage::TrackStatusRequest(track_status_request) => {
+                track_status_request.serialized_size()?
+            }
+            ControlMessage::TrackStatus(track_status) => track_status.serialized_size()?,
+            ControlMessage::GoAway(go_away) => go_away.serialized_size()?,
+            ControlMessage::ClientSetup(client_setup) => client_setup.serialized_size()?,
+            ControlMessage::ServerSetup(server_setup) => server_setup.serialized_size()?,
+        };
+        Ok(self.message_type().serialized_size()? + l)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::message_test::{create_test_message, MessageStructuredData};
+
+    #[test]
+    fn test_full_sequence_from_group_object() -> Result<()> {
+        let sequence: FullSequence = (4, 1).into();
+        assert_eq!(sequence, FullSequence::new(4, 1));
+        assert_eq!(sequence.as_group_object(), (4, 1));
+        let (group_id, object_id) = sequence.into();
+        assert_eq!((group_id, object_id), (4, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_sequence_ordering() -> Result<()> {
+        // Group takes precedence over object.
+        assert!(FullSequence::from((4, 9)) < FullSequence::from((5, 0)));
+        assert!(FullSequence::from((5, 0)) < FullSequence::from((5, 1)));
+        assert!(FullSequence::from((5, 1)) <= FullSequence::from((5, 1)));
+        assert_eq!(
+            FullSequence::from((5, 1)).next(),
+            FullSequence::from((5, 2))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_optional_full_sequence_serialize() -> Result<()> {
+        let mut buf = vec![];
+        let l = Some(FullSequence::new(12, 20)).serialize(&mut buf)?;
+        assert_eq!(l, 3);
+        assert_eq!(buf, [0x01, 0x0c, 0x14]);
+
+        let mut buf = vec![];
+        let l = Option::<FullSequence>::None.serialize(&mut buf)?;
+        assert_eq!(l, 1);
+        assert_eq!(buf, [0x00]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_optional_full_sequence_deserialize() -> Result<()> {
+        let mut r = &[0x01u8, 0x0c, 0x14][..];
+        let (sequence, l) = Option::<FullSequence>::deserialize(&mut r)?;
+        assert_eq!(sequence, Some(FullSequence::new(12, 20)));
+        assert_eq!(l, 3);
+
+        let mut r = &[0x00u8][..];
+        let (sequence, l) = Option::<FullSequence>::deserialize(&mut r)?;
+        assert_eq!(sequence, None);
+        assert_eq!(l, 1);
+
+        let mut r = &[0x02u8, 0x0c, 0x14][..];
+        assert!(Option::<FullSequence>::deserialize(&mut r).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_object_forwarding_preference() -> Result<()> {
+        assert_eq!(
+            MessageType::ObjectStream.get_object_forwarding_preference()?,
+            ObjectForwardingPreference::Object
+        );
+        assert_eq!(
+            MessageType::ObjectDatagram.get_object_forwarding_preference()?,
+            ObjectForwardingPreference::Datagram
+        );
+        assert_eq!(
+            MessageType::StreamHeaderTrack.get_object_forwarding_preference()?,
+            ObjectForwardingPreference::Track
+        );
+        assert_eq!(
+            MessageType::StreamHeaderGroup.get_object_forwarding_preference()?,
+            ObjectForwardingPreference::Group
+        );
+        // Control messages have no forwarding preference.
+        assert!(matches!(
+            MessageType::Subscribe.get_object_forwarding_preference(),
+            Err(Error::ErrInvalidMessageType(0x3))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_control_message_type() -> Result<()> {
+        for message_type in [
+            MessageType::SubscribeUpdate,
+            MessageType::Subscribe,
+            MessageType::SubscribeOk,
+            MessageType::SubscribeError,
+            MessageType::Announce,
+            MessageType::AnnounceOk,
+            MessageType::AnnounceError,
+            MessageType::UnAnnounce,
+            MessageType::UnSubscribe,
+            MessageType::SubscribeDone,
+            MessageType::AnnounceCancel,
+            MessageType::TrackStatusRequest,
+            MessageType::TrackStatus,
+            MessageType::GoAway,
+            MessageType::ClientSetup,
+            MessageType::ServerSetup,
+        ] {
+            let message = create_test_message(message_type, false);
+            if let MessageStructuredData::Control(control_message) = message.structured_data() {
+                assert_eq!(control_message.message_type(), message_type);
+                // The framed message starts with the same type code.
+                let mut packet = vec![];
+                control_message.serialize(&mut packet)?;
+                let (wire_type, _) = MessageType::deserialize(&mut &packet[..])?;
+                assert_eq!(wire_type, message_type);
+            } else {
+                return Err(Error::ErrInvalidMessageType(message_type as u64));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_forwarding_preference_from_wire() -> Result<()> {
+        assert_eq!(
+            MessageType::forwarding_preference_from_wire(0x0),
+            Some(ObjectForwardingPreference::Object)
+        );
+        assert_eq!(
+            MessageType::forwarding_preference_from_wire(0x50),
+            Some(ObjectForwardingPreference::Track)
+        );
+        assert_eq!(
+            MessageType::forwarding_preference_from_wire(0x51),
+            Some(ObjectForwardingPreference::Group)
+        );
+        assert!(MessageType::is_data_stream_type(0x51));
+        // Datagrams never arrive on a stream.
+        assert!(!MessageType::is_data_stream_type(0x1));
+        // Control messages.
+        assert!(!MessageType::is_data_stream_type(0x3));
+        assert!(!MessageType::is_data_stream_type(0x40));
+        // Unknown type.
+        assert!(!MessageType::is_data_stream_type(0x52));
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_sequence_as_key() -> Result<()> {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let hash = |sequence: FullSequence| {
+            let mut hasher = DefaultHasher::new();
+            sequence.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(FullSequence::new(4, 1)), hash((4, 1).into()));
+
+        let mut cache = std::collections::BTreeMap::new();
+        cache.insert(FullSequence::new(5, 0), "c");
+        cache.insert(FullSequence::new(4, 2), "b");
+        cache.insert(FullSequence::new(4, 1), "a");
+        assert_eq!(
+            cache.values().copied().collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            FullSequence::new(4, 9).cmp(&FullSequence::new(5, 0)),
+            std::cmp::Ordering::Less
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_track_name_as_key() -> Result<()> {
+        let foo_bar = FullTrackName::new("foo".to_string(), "bar".to_string());
+        let foo_baz = FullTrackName::new("foo".to_string(), "baz".to_string());
+        let fop_a = FullTrackName::new("fop".to_string(), "a".to_string());
+
+        let mut aliases = std::collections::HashMap::new();
+        aliases.insert(foo_bar.clone(), 1);
+        aliases.insert(foo_baz.clone(), 2);
+        assert_eq!(aliases.get(&foo_bar), Some(&1));
+        assert_eq!(aliases.get(&fop_a), None);
+
+        // Ordered by namespace, then name.
+        let ordered: std::collections::BTreeSet<_> =
+            [fop_a.clone(), foo_baz.clone(), foo_bar.clone()].into();
+        assert_eq!(
+            ordered.into_iter().collect::<Vec<_>>(),
+            vec![foo_bar, foo_baz, fop_a]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_track_namespace_too_long() -> Result<()> {
+        let mut packet = vec![];
+        "a".repeat(MAX_TRACK_NAMESPACE_BYTES + 1)
+            .serialize(&mut packet)?;
+        // Only the length prefix is needed to reject it.
+        let mut r = &packet[..2];
+        assert_eq!(
+            deserialize_track_namespace(&mut r),
+            Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "Track namespace is too long".to_string()
+            ))
+        );
+
+        let mut packet = vec![];
+        "a".repeat(MAX_TRACK_NAMESPACE_BYTES)
+            .serialize(&mut packet)?;
+        let (track_namespace, l) = deserialize_track_namespace(&mut &packet[..])?;
+        assert_eq!(track_namespace.len(), MAX_TRACK_NAMESPACE_BYTES);
+        assert_eq!(l, packet.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_role_wire_values() -> Result<()> {
+        for (role, value) in [
+            (Role::Publisher, 0x1u8),
+            (Role::Subscriber, 0x2),
+            (Role::PubSub, 0x3),
+        ] {
+            let mut w = vec![];
+            assert_eq!(role.serialize(&mut w)?, 1);
+            assert_eq!(w, vec![value]);
+            assert_eq!(Role::deserialize(&mut &w[..])?, (role, 1));
+        }
+        for value in [0x0u8, 0x4] {
+            assert_eq!(
+                Role::deserialize(&mut &[value][..]),
+                Err(Error::ErrInvalidRole(value as u64))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_type_wire_values() -> Result<()> {
+        let start = FullSequence::new(4, 1);
+        for filter_type in [
+            FilterType::LatestGroup,
+            FilterType::LatestObject,
+            FilterType::AbsoluteStart(start),
+            FilterType::AbsoluteRange(start, FullSequence::new(5, u64::MAX)),
+        ] {
+            let mut w = vec![];
+            let l = filter_type.serialize(&mut w)?;
+            assert_eq!(w[0], filter_type.value());
+            assert_eq!(FilterType::deserialize(&mut &w[..])?, (filter_type, l));
+        }
+        for value in [0x0u8, 0x5] {
+            assert_eq!(
+                FilterType::deserialize(&mut &[value][..]),
+                Err(Error::ErrInvalidFilterType(value as u64))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_track_namespace() -> Result<()> {
+        let mut r = &[0x00u8, 0x03, 0x62, 0x61, 0x72][..];
+        assert!(matches!(
+            FullTrackName::deserialize(&mut r),
+            Err(Error::ErrParseError(ErrorCode::ProtocolViolation, _))
+        ));
+
+        let mut r = &[0x03u8, 0x66, 0x6f, 0x6f, 0x03, 0x62, 0x61, 0x72][..];
+        let (full_track_name, l) = FullTrackName::deserialize(&mut r)?;
+        assert_eq!(
+            full_track_name,
+            FullTrackName::new("foo".to_string(), "bar".to_string())
+        );
+        assert_eq!(l, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_end_object_sentinel() -> Result<()> {
+        assert_eq!(decode_end_object(0), None);
+        assert_eq!(decode_end_object(1), Some(0));
+        assert_eq!(encode_end_object(None), 0);
+        assert_eq!(encode_end_object(Some(0)), 1);
+        for raw in [0, 1, 7] {
+            assert_eq!(encode_end_object(decode_end_object(raw)), raw);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_track_name_split_name() -> Result<()> {
+        let full_track_name = FullTrackName::new("live/room1".to_string(), "video".to_string());
+        assert_eq!(full_track_name.split_name(), ("live/room1", Some("video")));
+
+        let namespace_only = FullTrackName::new("live/room1".to_string(), String::new());
+        assert_eq!(namespace_only.split_name(), ("live/room1", None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_perspective_peer() {
+        for perspective in [Perspective::Server, Perspective::Client] {
+            assert_ne!(perspective.peer(), perspective);
+            assert_eq!(perspective.peer().peer(), perspective);
+            assert_ne!(perspective.is_server(), perspective.peer().is_server());
+        }
+        assert!(Perspective::Server.is_server());
+    }
+}
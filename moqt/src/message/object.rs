@@ -1,5 +1,13 @@
-use crate::message::MessageType;
+use crate::message::message_parser::ErrorCode;
+use crate::message::{FullSequence, MessageType};
+use crate::{Deserializer, Error, Result, Serializer};
+use bytes::{Buf, BufMut};
+use std::fmt::{Display, Formatter};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ObjectForwardingPreference {
     #[default]
@@ -10,6 +18,8 @@ pub enum ObjectForwardingPreference {
 }
 
 impl ObjectForwardingPreference {
+    /// The message type objects with this preference are sent with. This is
+    /// the inverse of MessageType::get_object_forwarding_preference.
     pub(crate) fn get_message_type(&self) -> MessageType {
         match *self {
             ObjectForwardingPreference::Object => MessageType::ObjectStream,
@@ -20,6 +30,10 @@ impl ObjectForwardingPreference {
     }
 }
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ObjectStatus {
     #[default]
@@ -31,22 +45,71 @@ pub enum ObjectStatus {
     Invalid = 0x5,
 }
 
-impl From<u64> for ObjectStatus {
-    fn from(value: u64) -> Self {
+impl ObjectStatus {
+    /// Whether the status marks the end of a group or of the whole track, so
+    /// no further objects follow in that scope.
+    pub fn is_terminal(&self) -> bool {
+        *self == ObjectStatus::EndOfGroup || *self == ObjectStatus::EndOfTrack
+    }
+}
+
+impl Display for ObjectStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match *self {
+            ObjectStatus::Normal => "NORMAL",
+            ObjectStatus::ObjectDoesNotExist => "OBJECT_DOES_NOT_EXIST",
+            ObjectStatus::GroupDoesNotExist => "GROUP_DOES_NOT_EXIST",
+            ObjectStatus::EndOfGroup => "END_OF_GROUP",
+            ObjectStatus::EndOfTrack => "END_OF_TRACK",
+            ObjectStatus::Invalid => "INVALID",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TryFrom<u64> for ObjectStatus {
+    type Error = Error;
+
+    fn try_from(value: u64) -> Result<Self> {
         match value {
-            0x0 => Self::Normal,
-            0x1 => Self::ObjectDoesNotExist,
-            0x2 => Self::GroupDoesNotExist,
-            0x3 => Self::EndOfGroup,
-            0x4 => Self::EndOfTrack,
-            _ => Self::Invalid,
+            0x0 => Ok(Self::Normal),
+            0x1 => Ok(Self::ObjectDoesNotExist),
+            0x2 => Ok(Self::GroupDoesNotExist),
+            0x3 => Ok(Self::EndOfGroup),
+            0x4 => Ok(Self::EndOfTrack),
+            _ => Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "Invalid object status".to_string(),
+            )),
         }
     }
 }
 
+impl Deserializer for ObjectStatus {
+    fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
+        let (status, sl) = u64::deserialize(r)?;
+        Ok((ObjectStatus::try_from(status)?, sl))
+    }
+}
+
+impl Serializer for ObjectStatus {
+    fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
+        if *self == ObjectStatus::Invalid {
+            return Err(Error::ErrInvalidObjectType(
+                "Cannot send an invalid object status".to_string(),
+            ));
+        }
+        (*self as u64).serialize(w)
+    }
+}
+
 /// The data contained in every Object message, although the message type
 /// implies some of the values. |payload_length| has no value if the length
 /// is unknown (because it runs to the end of the stream.)
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub struct ObjectHeader {
     pub subscribe_id: u64,
@@ -58,3 +121,267 @@ pub struct ObjectHeader {
     pub object_forwarding_preference: ObjectForwardingPreference,
     pub object_payload_length: Option<u64>,
 }
+
+/// Transmission priority of an object. A lower send order is sent first;
+/// objects with the same send order are sent in group order.
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SendPriority {
+    pub send_order: u64,
+    pub group_id: u64,
+}
+
+impl SendPriority {
+    pub fn new(send_order: u64, group_id: u64) -> Self {
+        Self {
+            send_order,
+            group_id,
+        }
+    }
+
+    /// Whether an object with this priority should be sent before one with
+    /// |other|.
+    pub fn more_important_than(&self, other: &Self) -> bool {
+        self < other
+    }
+}
+
+/// Orders from most to least important.
+impl Ord for SendPriority {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.send_order
+            .cmp(&other.send_order)
+            .then(self.group_id.cmp(&other.group_id))
+    }
+}
+
+impl PartialOrd for SendPriority {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ObjectHeader {
+    /// A header for an OBJECT_DATAGRAM. Datagrams carry no payload length;
+    /// the payload runs to the end of the datagram.
+    pub fn for_datagram(
+        subscribe_id: u64,
+        track_alias: u64,
+        sequence: FullSequence,
+        object_send_order: u64,
+        object_status: ObjectStatus,
+    ) -> Self {
+        Self {
+            subscribe_id,
+            track_alias,
+            group_id: sequence.group_id,
+            object_id: sequence.object_id,
+            object_send_order,
+            object_status,
+            object_forwarding_preference: ObjectForwardingPreference::Datagram,
+            object_payload_length: None,
+        }
+    }
+
+    /// A header for an object sent on a stream, with the payload length the
+    /// framer requires for track and group streams.
+    pub fn for_stream(
+        object_forwarding_preference: ObjectForwardingPreference,
+        subscribe_id: u64,
+        track_alias: u64,
+        sequence: FullSequence,
+        object_send_order: u64,
+        object_status: ObjectStatus,
+        object_payload_length: u64,
+    ) -> Result<Self> {
+        if object_forwarding_preference == ObjectForwardingPreference::Datagram {
+            return Err(Error::ErrInvalidObjectType(
+                "Datagram objects are not sent on a stream".to_string(),
+            ));
+        }
+        Ok(Self {
+            subscribe_id,
+            track_alias,
+            group_id: sequence.group_id,
+            object_id: sequence.object_id,
+            object_send_order,
+            object_status,
+            object_forwarding_preference,
+            object_payload_length: Some(object_payload_length),
+        })
+    }
+
+    /// Whether both headers describe the same object of the same track,
+    /// regardless of status, send order or payload length. Derived equality
+    /// compares every field, so a retransmission with a different send order
+    /// is not equal but is at the same location.
+    pub fn same_location(&self, other: &Self) -> bool {
+        self.track_alias == other.track_alias
+            && self.group_id == other.group_id
+            && self.object_id == other.object_id
+    }
+
+    pub fn send_priority(&self) -> SendPriority {
+        SendPriority::new(self.object_send_order, self.group_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_object_status_try_from() -> Result<()> {
+        assert_eq!(ObjectStatus::try_from(0)?, ObjectStatus::Normal);
+        assert_eq!(ObjectStatus::try_from(4)?, ObjectStatus::EndOfTrack);
+        for status in [5, 6, 99] {
+            assert!(matches!(
+                ObjectStatus::try_from(status),
+                Err(Error::ErrParseError(ErrorCode::ProtocolViolation, _))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_status_display_and_terminal() -> Result<()> {
+        for (status, name, terminal) in [
+            (ObjectStatus::Normal, "NORMAL", false),
+            (
+                ObjectStatus::ObjectDoesNotExist,
+                "OBJECT_DOES_NOT_EXIST",
+                false,
+            ),
+            (
+                ObjectStatus::GroupDoesNotExist,
+                "GROUP_DOES_NOT_EXIST",
+                false,
+            ),
+            (ObjectStatus::EndOfGroup, "END_OF_GROUP", true),
+            (ObjectStatus::EndOfTrack, "END_OF_TRACK", true),
+            (ObjectStatus::Invalid, "INVALID", false),
+        ] {
+            assert_eq!(status.to_string(), name);
+            assert_eq!(status.is_terminal(), terminal);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_forwarding_preference_message_type_bijection() -> Result<()> {
+        for preference in [
+            ObjectForwardingPreference::Object,
+            ObjectForwardingPreference::Datagram,
+            ObjectForwardingPreference::Track,
+            ObjectForwardingPreference::Group,
+        ] {
+            let message_type = preference.get_message_type();
+            assert!(message_type.is_object_message());
+            assert_eq!(message_type.get_object_forwarding_preference()?, preference);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_priority_ordering() -> Result<()> {
+        let high = SendPriority::new(0x10, 7);
+        let low = SendPriority::new(0xf0, 0);
+        assert!(high.more_important_than(&low));
+        assert!(!low.more_important_than(&high));
+        assert!(high < low);
+
+        // Ties on send order are broken by group.
+        assert!(SendPriority::new(0x10, 1).more_important_than(&SendPriority::new(0x10, 2)));
+        assert!(!high.more_important_than(&high));
+
+        let object_header = ObjectHeader {
+            group_id: 5,
+            object_send_order: 0x10,
+            ..Default::default()
+        };
+        assert_eq!(object_header.send_priority(), SendPriority::new(0x10, 5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_header_same_location() -> Result<()> {
+        let original = ObjectHeader {
+            subscribe_id: 3,
+            track_alias: 4,
+            group_id: 5,
+            object_id: 6,
+            object_send_order: 7,
+            object_status: ObjectStatus::Normal,
+            object_forwarding_preference: ObjectForwardingPreference::Group,
+            object_payload_length: Some(3),
+        };
+        let mut retransmit = original;
+        retransmit.object_send_order = 1;
+        assert_ne!(original, retransmit);
+        assert!(original.same_location(&retransmit));
+
+        let mut next = original;
+        next.object_id = 7;
+        assert!(!original.same_location(&next));
+
+        let mut other_track = original;
+        other_track.track_alias = 5;
+        assert!(!original.same_location(&other_track));
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_status_serialize() -> Result<()> {
+        let mut buf = vec![];
+        assert_eq!(ObjectStatus::EndOfGroup.serialize(&mut buf)?, 1);
+        assert_eq!(buf, [0x03]);
+        let (status, l) = ObjectStatus::deserialize(&mut &buf[..])?;
+        assert_eq!((status, l), (ObjectStatus::EndOfGroup, 1));
+
+        let mut buf = vec![];
+        assert!(ObjectStatus::Invalid.serialize(&mut buf).is_err());
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_header_constructors() -> Result<()> {
+        let datagram =
+            ObjectHeader::for_datagram(3, 4, FullSequence::new(5, 6), 7, ObjectStatus::Normal);
+        assert_eq!(
+            datagram.object_forwarding_preference,
+            ObjectForwardingPreference::Datagram
+        );
+        assert_eq!(datagram.object_payload_length, None);
+        assert_eq!((datagram.group_id, datagram.object_id), (5, 6));
+
+        let group = ObjectHeader::for_stream(
+            ObjectForwardingPreference::Group,
+            3,
+            4,
+            FullSequence::new(5, 6),
+            7,
+            ObjectStatus::Normal,
+            3,
+        )?;
+        assert_eq!(group.object_payload_length, Some(3));
+        assert!(group.same_location(&datagram));
+
+        assert!(matches!(
+            ObjectHeader::for_stream(
+                ObjectForwardingPreference::Datagram,
+                3,
+                4,
+                FullSequence::new(5, 6),
+                7,
+                ObjectStatus::Normal,
+                3,
+            ),
+            Err(Error::ErrInvalidObjectType(_))
+        ));
+        Ok(())
+    }
+}
@@ -1,20 +1,40 @@
 use crate::message::message_parser::ErrorCode;
-use crate::message::{Role, Version};
-use crate::serde::parameters::ParameterKey;
-use crate::{Deserializer, Error, Parameters, Result, Serializer};
+use crate::message::{MessageType, Role, Version, MAX_PARAMETERS};
+use crate::serde::parameters::{check_parameter, deserialize_int_parameter, ParameterKey};
+use crate::{Deserializer, Error, Parameters, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct ServerSetup {
     pub supported_version: Version,
     pub role: Option<Role>,
 }
 
+impl ServerSetup {
+    fn parameters(&self) -> Result<Parameters> {
+        let mut parameters = Parameters::new();
+        if let Some(role) = self.role.as_ref() {
+            parameters.insert(ParameterKey::Role, *role)?;
+        }
+        Ok(parameters)
+    }
+}
+
 impl Deserializer for ServerSetup {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
         let (supported_version, mut tl) = Version::deserialize(r)?;
 
-        let (num_params, npl) = u64::deserialize(r)?;
+        let (num_params, npl) = usize::deserialize(r)?;
+        if num_params > MAX_PARAMETERS {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "Too many parameters in SERVER_SETUP".to_string(),
+            ));
+        }
         tl += npl;
 
         let mut role: Option<Role> = None;
@@ -30,6 +50,13 @@ impl Deserializer for ServerSetup {
                 return Err(Error::ErrBufferTooShort);
             }
 
+            if !check_parameter(MessageType::ServerSetup, key)? {
+                // Skip unknown parameters.
+                r.advance(size);
+                tl += size;
+                continue;
+            }
+
             if key == ParameterKey::Role as u64 {
                 if role.is_some() {
                     return Err(Error::ErrParseError(
@@ -37,27 +64,15 @@ impl Deserializer for ServerSetup {
                         "ROLE parameter appears twice in SETUP".to_string(),
                     ));
                 }
-                let (r, rl) = u64::deserialize(r)?;
+                let (r, rl) = deserialize_int_parameter(r, size, ParameterKey::Role)?;
                 tl += rl;
 
-                if rl != size {
-                    return Err(Error::ErrParseError(
-                        ErrorCode::ProtocolViolation,
-                        "Parameter length does not match varint encoding".to_string(),
-                    ));
-                }
-
                 role = Some(r.try_into().map_err(|_| {
                     Error::ErrParseError(
                         ErrorCode::ProtocolViolation,
-                        "Invalid ROLE parameter".to_string(),
+                        format!("ROLE parameter value {} is out of range", r),
                     )
                 })?);
-            } else if key == ParameterKey::Path as u64 {
-                return Err(Error::ErrParseError(
-                    ErrorCode::ProtocolViolation,
-                    "PATH parameter in SERVER_SETUP".to_string(),
-                ));
             }
         }
 
@@ -81,12 +96,15 @@ impl Deserializer for ServerSetup {
 impl Serializer for ServerSetup {
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
         let mut l = self.supported_version.serialize(w)?;
+        l += self.parameters()?.serialize(w)?;
+        Ok(l)
+    }
+}
 
-        let mut parameters = Parameters::new();
-        if let Some(role) = self.role.as_ref() {
-            parameters.insert(ParameterKey::Role, *role)?;
-        }
-        l += parameters.serialize(w)?;
+impl SerializedSize for ServerSetup {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.supported_version.serialized_size()?;
+        l += self.parameters()?.serialized_size()?;
         Ok(l)
     }
 }
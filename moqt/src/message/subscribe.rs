@@ -1,9 +1,15 @@
 use crate::message::message_parser::ErrorCode;
-use crate::message::FilterType;
-use crate::serde::parameters::ParameterKey;
-use crate::{Deserializer, Error, Parameters, Result, Serializer};
+use crate::message::{
+    deserialize_track_namespace, FilterType, FullSequence, MessageType, MAX_PARAMETERS,
+};
+use crate::serde::parameters::{check_parameter, ParameterKey};
+use crate::{Deserializer, Error, Parameters, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct Subscribe {
     pub subscribe_id: u64,
@@ -17,18 +23,64 @@ pub struct Subscribe {
     pub authorization_info: Option<String>,
 }
 
+impl Subscribe {
+    /// Returns the inclusive start and the optional inclusive end of an
+    /// absolute subscription, or None for LatestGroup/LatestObject. When the
+    /// whole end group is requested, the end object is u64::MAX.
+    pub fn object_range(&self) -> Option<(FullSequence, Option<FullSequence>)> {
+        self.filter_type.object_range()
+    }
+
+    /// Returns the absolute first object of the subscription. Absolute filters
+    /// carry it on the wire; LatestGroup and LatestObject are relative to the
+    /// track's |next_sequence|, the first object not yet published.
+    /// LatestObject starts at the last published object of that group. If the
+    /// group has none yet (object_id 0), the previous group's last object is
+    /// not known here, so the subscription starts at |next_sequence| itself.
+    pub fn resolve_start(&self, next_sequence: FullSequence) -> FullSequence {
+        match self.filter_type {
+            FilterType::LatestGroup => FullSequence::new(next_sequence.group_id, 0),
+            FilterType::LatestObject if next_sequence.object_id == 0 => next_sequence,
+            FilterType::LatestObject => {
+                FullSequence::new(next_sequence.group_id, next_sequence.object_id - 1)
+            }
+            FilterType::AbsoluteStart(start) | FilterType::AbsoluteRange(start, _) => start,
+        }
+    }
+    fn parameters(&self) -> Result<Option<Parameters>> {
+        let authorization_info = if let Some(authorization_info) = self.authorization_info.as_ref()
+        {
+            authorization_info
+        } else {
+            return Ok(None);
+        };
+        let mut parameters = Parameters::new();
+        parameters.insert(
+            ParameterKey::AuthorizationInfo,
+            authorization_info.to_string(),
+        )?;
+        Ok(Some(parameters))
+    }
+}
+
 impl Deserializer for Subscribe {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
         let (subscribe_id, sil) = u64::deserialize(r)?;
 
         let (track_alias, tal) = u64::deserialize(r)?;
-        let (track_namespace, tnsl) = String::deserialize(r)?;
+        let (track_namespace, tnsl) = deserialize_track_namespace(r)?;
         let (track_name, tnl) = String::deserialize(r)?;
 
         let (filter_type, ftl) = FilterType::deserialize(r)?;
 
         let mut authorization_info: Option<String> = None;
-        let (num_params, mut pl) = u64::deserialize(r)?;
+        let (num_params, mut pl) = usize::deserialize(r)?;
+        if num_params > MAX_PARAMETERS {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "Too many parameters in SUBSCRIBE".to_string(),
+            ));
+        }
         // Parse parameters
         for _ in 0..num_params {
             let (key, kl) = u64::deserialize(r)?;
@@ -40,6 +92,13 @@ impl Deserializer for Subscribe {
                 return Err(Error::ErrBufferTooShort);
             }
 
+            if !check_parameter(MessageType::Subscribe, key)? {
+                // Skip unknown parameters.
+                r.advance(size);
+                pl += size;
+                continue;
+            }
+
             if key == ParameterKey::AuthorizationInfo as u64 {
                 if authorization_info.is_some() {
                     return Err(Error::ErrParseError(
@@ -75,22 +134,27 @@ impl Deserializer for Subscribe {
 impl Serializer for Subscribe {
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
         let mut l = self.subscribe_id.serialize(w)?;
-
         l += self.track_alias.serialize(w)?;
         l += self.track_namespace.serialize(w)?;
         l += self.track_name.serialize(w)?;
-
         l += self.filter_type.serialize(w)?;
-
-        if let Some(authorization_info) = self.authorization_info.as_ref() {
-            let mut parameters = Parameters::new();
-            parameters.insert(
-                ParameterKey::AuthorizationInfo,
-                authorization_info.to_string(),
-            )?;
+        if let Some(parameters) = self.parameters()? {
             l += parameters.serialize(w)?;
         }
+        Ok(l)
+    }
+}
 
+impl SerializedSize for Subscribe {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.subscribe_id.serialized_size()?;
+        l += self.track_alias.serialized_size()?;
+        l += self.track_namespace.serialized_size()?;
+        l += self.track_name.serialized_size()?;
+        l += self.filter_type.serialized_size()?;
+        if let Some(parameters) = self.parameters()? {
+            l += parameters.serialized_size()?;
+        }
         Ok(l)
     }
 }
@@ -98,7 +162,7 @@ impl Serializer for Subscribe {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::message::{ControlMessage, FullSequence};
+    use crate::message::ControlMessage;
     use std::io::Cursor;
 
     #[test]
@@ -108,7 +172,7 @@ mod test {
             0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
             0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
             0x03, // Filter type: Absolute Start
-            0x04, // start_group = 4 (relative previous)
+            0x04, // start_group = 4 (absolute)
             0x01, // start_object = 1 (absolute)
             // No EndGroup or EndObject
             0x01, // 1 parameter
@@ -138,4 +202,158 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_subscribe_parameters() -> Result<()> {
+        let packet: Vec<u8> = vec![
+            0x03, 0x01, 0x02, // id and alias
+            0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+            0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
+            0x01, // Filter type: Latest Group
+            0x02, // 2 parameters
+            0x20, 0x02, 0x00, 0x00, // unknown parameter
+            0x02, 0x03, 0x62, 0x61, 0x72, // authorization_info = "bar"
+        ];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(packet.as_ref());
+        let (message, len) = ControlMessage::deserialize(&mut cursor)?;
+        assert_eq!(len, packet.len());
+        if let ControlMessage::Subscribe(subscribe) = message {
+            assert_eq!(subscribe.authorization_info, Some("bar".to_string()));
+        } else {
+            return Err(Error::ErrInvalidMessageType(0x03));
+        }
+
+        let packet: Vec<u8> = vec![
+            0x03, 0x01, 0x02, // id and alias
+            0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+            0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
+            0x01, // Filter type: Latest Group
+            0x01, // 1 parameter
+            0x01, 0x03, 0x66, 0x6f, 0x6f, // path = "foo"
+        ];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(packet.as_ref());
+        assert_eq!(
+            ControlMessage::deserialize(&mut cursor),
+            Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "PATH parameter is not allowed in SUBSCRIBE".to_string()
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe_object_range() -> Result<()> {
+        let mut subscribe = Subscribe {
+            filter_type: FilterType::LatestGroup,
+            ..Default::default()
+        };
+        assert_eq!(subscribe.object_range(), None);
+
+        subscribe.filter_type = FilterType::LatestObject;
+        assert_eq!(subscribe.object_range(), None);
+
+        subscribe.filter_type = FilterType::AbsoluteStart((4, 1).into());
+        assert_eq!(subscribe.object_range(), Some(((4, 1).into(), None)));
+
+        subscribe.filter_type = FilterType::AbsoluteRange((4, 1).into(), (6, 2).into());
+        assert_eq!(
+            subscribe.object_range(),
+            Some(((4, 1).into(), Some((6, 2).into())))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe_object_range_whole_last_group() -> Result<()> {
+        let packet: Vec<u8> = vec![
+            0x01, 0x02, // id and alias
+            0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+            0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
+            0x04, // Filter type: Absolute Range
+            0x04, 0x01, // start = (4, 1)
+            0x07, 0x00, // end_group = 7, end_object = whole group
+            0x00, // no parameters
+        ];
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(packet.as_ref());
+        let (subscribe, _) = Subscribe::deserialize(&mut cursor)?;
+        assert_eq!(
+            subscribe.object_range(),
+            Some(((4, 1).into(), Some((7, u64::MAX).into())))
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde-derive")]
+    #[test]
+    fn test_subscribe_json_round_trip() -> Result<()> {
+        let subscribe = Subscribe {
+            subscribe_id: 1,
+            track_alias: 2,
+            track_namespace: "foo".to_string(),
+            track_name: "abcd".to_string(),
+            filter_type: FilterType::AbsoluteRange((4, 1).into(), (6, u64::MAX).into()),
+            authorization_info: Some("bar".to_string()),
+        };
+        let json =
+            serde_json::to_string(&subscribe).map_err(|err| Error::ErrOther(err.to_string()))?;
+        let parsed: Subscribe =
+            serde_json::from_str(&json).map_err(|err| Error::ErrOther(err.to_string()))?;
+        assert_eq!(subscribe, parsed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_start() -> Result<()> {
+        let next_sequence = FullSequence::new(7, 3);
+        let mut subscribe = Subscribe {
+            filter_type: FilterType::LatestGroup,
+            ..Default::default()
+        };
+        assert_eq!(
+            subscribe.resolve_start(next_sequence),
+            FullSequence::new(7, 0)
+        );
+
+        subscribe.filter_type = FilterType::LatestObject;
+        assert_eq!(
+            subscribe.resolve_start(next_sequence),
+            FullSequence::new(7, 2)
+        );
+
+        subscribe.filter_type = FilterType::AbsoluteStart(FullSequence::new(4, 1));
+        assert_eq!(
+            subscribe.resolve_start(next_sequence),
+            FullSequence::new(4, 1)
+        );
+        subscribe.filter_type =
+            FilterType::AbsoluteRange(FullSequence::new(4, 1), FullSequence::new(6, 0));
+        assert_eq!(
+            subscribe.resolve_start(next_sequence),
+            FullSequence::new(4, 1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_start_latest_object_at_group_start() -> Result<()> {
+        let subscribe = Subscribe {
+            filter_type: FilterType::LatestObject,
+            ..Default::default()
+        };
+        // Group 6 has no objects yet, so the subscription begins with its
+        // first one rather than reaching back into group 5.
+        assert_eq!(
+            subscribe.resolve_start(FullSequence::new(6, 0)),
+            FullSequence::new(6, 0)
+        );
+        assert_eq!(
+            subscribe.resolve_start(FullSequence::new(0, 0)),
+            FullSequence::new(0, 0)
+        );
+        Ok(())
+    }
 }
@@ -1,8 +1,12 @@
 use crate::message::message_parser::ErrorCode;
 use crate::message::FullSequence;
-use crate::{Deserializer, Error, Result, Serializer};
+use crate::{Deserializer, Error, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum SubscribeDoneCode {
     #[default]
@@ -15,6 +19,10 @@ pub enum SubscribeDoneCode {
     Expired = 0x6,
 }
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct SubscribeDone {
     pub subscribe_id: u64,
@@ -32,7 +40,7 @@ impl Deserializer for SubscribeDone {
         let (status_code, scl) = u64::deserialize(r)?;
         let (reason_phrase, rpl) = String::deserialize(r)?;
 
-        let (exist, el) = bool::deserialize(r).map_err(|err| {
+        let (final_group_object, fgol) = Option::<FullSequence>::deserialize(r).map_err(|err| {
             if let Error::ErrInvalidBooleanValue(b) = err {
                 Error::ErrParseError(
                     ErrorCode::ProtocolViolation,
@@ -42,14 +50,6 @@ impl Deserializer for SubscribeDone {
                 err
             }
         })?;
-        let mut tl = sil + scl + rpl + el;
-        let final_group_object = if exist {
-            let (final_group_object, fgol) = FullSequence::deserialize(r)?;
-            tl += fgol;
-            Some(final_group_object)
-        } else {
-            None
-        };
 
         Ok((
             Self {
@@ -60,7 +60,7 @@ impl Deserializer for SubscribeDone {
 
                 final_group_object,
             },
-            tl,
+            sil + scl + rpl + fgol,
         ))
     }
 }
@@ -72,16 +72,22 @@ impl Serializer for SubscribeDone {
         l += self.status_code.serialize(w)?;
         l += self.reason_phrase.serialize(w)?;
 
-        l += if let Some(group_object_pair) = self.final_group_object.as_ref() {
-            true.serialize(w)? + group_object_pair.serialize(w)?
-        } else {
-            false.serialize(w)?
-        };
+        l += self.final_group_object.serialize(w)?;
 
         Ok(l)
     }
 }
 
+impl SerializedSize for SubscribeDone {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.subscribe_id.serialized_size()?;
+        l += self.status_code.serialized_size()?;
+        l += self.reason_phrase.serialized_size()?;
+        l += self.final_group_object.serialized_size()?;
+        Ok(l)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -117,4 +123,83 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_subscribe_done_no_content() -> Result<()> {
+        let expected_packet: Vec<u8> = vec![
+            0x0b, 0x02, 0x03, // subscribe_id = 2, error_code = 3,
+            0x02, 0x68, 0x69, // reason_phrase = "hi"
+            0x00, // no content
+        ];
+
+        let expected_message = ControlMessage::SubscribeDone(SubscribeDone {
+            subscribe_id: 2,
+            status_code: 3,
+            reason_phrase: "hi".to_string(),
+            final_group_object: None,
+        });
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(expected_packet.as_ref());
+        let (actual_message, actual_len) = ControlMessage::deserialize(&mut cursor)?;
+        assert_eq!(expected_message, actual_message);
+        assert_eq!(expected_packet.len(), actual_len);
+
+        let mut actual_packet = vec![];
+        let _ = expected_message.serialize(&mut actual_packet)?;
+        assert_eq!(expected_packet, actual_packet);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe_done_zero_final_id() -> Result<()> {
+        let expected_packet: Vec<u8> = vec![
+            0x0b, 0x02, 0x03, // subscribe_id = 2, error_code = 3,
+            0x02, 0x68, 0x69, // reason_phrase = "hi"
+            0x01, 0x00, 0x00, // final_id = (0, 0)
+        ];
+        let expected_message = ControlMessage::SubscribeDone(SubscribeDone {
+            subscribe_id: 2,
+            status_code: 3,
+            reason_phrase: "hi".to_string(),
+            final_group_object: Some(FullSequence::new(0, 0)),
+        });
+
+        let mut actual_packet = vec![];
+        let _ = expected_message.serialize(&mut actual_packet)?;
+        assert_eq!(expected_packet, actual_packet);
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(expected_packet.as_ref());
+        let (actual_message, actual_len) = ControlMessage::deserialize(&mut cursor)?;
+        assert_eq!(expected_message, actual_message);
+        assert_eq!(expected_packet.len(), actual_len);
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe_done_empty_reason() -> Result<()> {
+        let expected_packet: Vec<u8> = vec![
+            0x0b, 0x02, 0x03, // subscribe_id = 2, error_code = 3,
+            0x00, // reason_phrase = ""
+            0x01, 0x08, 0x0c, // final_id = (8,12)
+        ];
+
+        let expected_message = ControlMessage::SubscribeDone(SubscribeDone {
+            subscribe_id: 2,
+            status_code: 3,
+            reason_phrase: String::new(),
+            final_group_object: Some(FullSequence::new(8, 12)),
+        });
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(expected_packet.as_ref());
+        let (actual_message, actual_len) = ControlMessage::deserialize(&mut cursor)?;
+        assert_eq!(expected_message, actual_message);
+        assert_eq!(expected_packet.len(), actual_len);
+
+        let mut actual_packet = vec![];
+        let _ = expected_message.serialize(&mut actual_packet)?;
+        assert_eq!(expected_packet, actual_packet);
+
+        Ok(())
+    }
 }
@@ -1,6 +1,10 @@
-use crate::{Deserializer, Result, Serializer};
+use crate::{Deserializer, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum SubscribeErrorCode {
     #[default]
@@ -9,6 +13,10 @@ pub enum SubscribeErrorCode {
     RetryTrackAlias = 2,
 }
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct SubscribeError {
     pub subscribe_id: u64,
@@ -55,6 +63,16 @@ impl Serializer for SubscribeError {
     }
 }
 
+impl SerializedSize for SubscribeError {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.subscribe_id.serialized_size()?;
+        l += self.error_code.serialized_size()?;
+        l += self.reason_phrase.serialized_size()?;
+        l += self.track_alias.serialized_size()?;
+        Ok(l)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -88,4 +106,32 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_subscribe_error_empty_reason() -> Result<()> {
+        let expected_packet: Vec<u8> = vec![
+            0x05, 0x02, // subscribe_id = 2
+            0x01, // error_code = 1
+            0x00, // reason_phrase = ""
+            0x04, // track_alias = 4,
+        ];
+
+        let expected_message = ControlMessage::SubscribeError(SubscribeError {
+            subscribe_id: 2,
+            error_code: SubscribeErrorCode::InvalidRange as u64,
+            reason_phrase: String::new(),
+            track_alias: 4,
+        });
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(expected_packet.as_ref());
+        let (actual_message, actual_len) = ControlMessage::deserialize(&mut cursor)?;
+        assert_eq!(expected_message, actual_message);
+        assert_eq!(expected_packet.len(), actual_len);
+
+        let mut actual_packet = vec![];
+        let _ = expected_message.serialize(&mut actual_packet)?;
+        assert_eq!(expected_packet, actual_packet);
+
+        Ok(())
+    }
 }
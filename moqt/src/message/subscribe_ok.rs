@@ -1,8 +1,12 @@
 use crate::message::message_parser::ErrorCode;
 use crate::message::FullSequence;
-use crate::{Deserializer, Error, Result, Serializer};
+use crate::{Deserializer, Error, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct SubscribeOk {
     pub subscribe_id: u64,
@@ -18,24 +22,17 @@ impl Deserializer for SubscribeOk {
 
         let (expires, el) = u64::deserialize(r)?;
 
-        let (exist, l) = bool::deserialize(r).map_err(|err| {
-            if let Error::ErrInvalidBooleanValue(b) = err {
-                Error::ErrParseError(
-                    ErrorCode::ProtocolViolation,
-                    format!("SUBSCRIBE_OK ContentExists has invalid value {}", b),
-                )
-            } else {
-                err
-            }
-        })?;
-        let mut tl = sil + el + l;
-        let largest_group_object = if exist {
-            let (largest_group_object, lgol) = FullSequence::deserialize(r)?;
-            tl += lgol;
-            Some(largest_group_object)
-        } else {
-            None
-        };
+        let (largest_group_object, lgol) =
+            Option::<FullSequence>::deserialize(r).map_err(|err| {
+                if let Error::ErrInvalidBooleanValue(b) = err {
+                    Error::ErrParseError(
+                        ErrorCode::ProtocolViolation,
+                        format!("SUBSCRIBE_OK ContentExists has invalid value {}", b),
+                    )
+                } else {
+                    err
+                }
+            })?;
 
         Ok((
             Self {
@@ -45,7 +42,7 @@ impl Deserializer for SubscribeOk {
 
                 largest_group_object,
             },
-            tl,
+            sil + el + lgol,
         ))
     }
 }
@@ -56,16 +53,21 @@ impl Serializer for SubscribeOk {
 
         l += self.expires.serialize(w)?;
 
-        l += if let Some(largest_group_object) = self.largest_group_object.as_ref() {
-            true.serialize(w)? + largest_group_object.serialize(w)?
-        } else {
-            false.serialize(w)?
-        };
+        l += self.largest_group_object.serialize(w)?;
 
         Ok(l)
     }
 }
 
+impl SerializedSize for SubscribeOk {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.subscribe_id.serialized_size()?;
+        l += self.expires.serialized_size()?;
+        l += self.largest_group_object.serialized_size()?;
+        Ok(l)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -99,4 +101,29 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_subscribe_ok_no_content() -> Result<()> {
+        let expected_packet: Vec<u8> = vec![
+            0x04, 0x01, 0x03, // subscribe_id = 1, expires = 3
+            0x00, // no content
+        ];
+
+        let expected_message = ControlMessage::SubscribeOk(SubscribeOk {
+            subscribe_id: 1,
+            expires: 3,
+            largest_group_object: None,
+        });
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(expected_packet.as_ref());
+        let (actual_message, actual_len) = ControlMessage::deserialize(&mut cursor)?;
+        assert_eq!(expected_message, actual_message);
+        assert_eq!(expected_packet.len(), actual_len);
+
+        let mut actual_packet = vec![];
+        let _ = expected_message.serialize(&mut actual_packet)?;
+        assert_eq!(expected_packet, actual_packet);
+
+        Ok(())
+    }
 }
@@ -1,10 +1,17 @@
 use crate::message::message_parser::ErrorCode;
-use crate::message::FullSequence;
-use crate::serde::parameters::ParameterKey;
-use crate::{Deserializer, Parameters, Serializer};
+use crate::message::subscribe::Subscribe;
+use crate::message::{
+    decode_end_object, encode_end_object, FilterType, FullSequence, MessageType, MAX_PARAMETERS,
+};
+use crate::serde::parameters::{check_parameter, ParameterKey};
+use crate::{Deserializer, Parameters, SerializedSize, Serializer};
 use crate::{Error, Result};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct SubscribeUpdate {
     pub subscribe_id: u64,
@@ -15,6 +22,84 @@ pub struct SubscribeUpdate {
     pub authorization_info: Option<String>,
 }
 
+impl SubscribeUpdate {
+    /// A SUBSCRIBE_UPDATE may only narrow the subscription it updates. The
+    /// start is not checked for LatestGroup/LatestObject subscriptions, since
+    /// it depends on the track state when the SUBSCRIBE arrived.
+    pub fn is_valid_update(&self, original: &Subscribe) -> Result<()> {
+        self.is_valid_filter_update(&original.filter_type)
+    }
+
+    /// Same as is_valid_update, against the current |filter_type| of the
+    /// subscription, which may already have been narrowed by earlier updates.
+    pub fn is_valid_filter_update(&self, filter_type: &FilterType) -> Result<()> {
+        let (start, end) = if let Some(range) = filter_type.object_range() {
+            range
+        } else {
+            return Ok(());
+        };
+        if self.start_group_object < start {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "SUBSCRIBE_UPDATE moves the start earlier".to_string(),
+            ));
+        }
+        if let Some(end) = end {
+            match self.end_group_object {
+                None => {
+                    return Err(Error::ErrParseError(
+                        ErrorCode::ProtocolViolation,
+                        "SUBSCRIBE_UPDATE removes the end".to_string(),
+                    ));
+                }
+                Some(update_end) if update_end > end => {
+                    return Err(Error::ErrParseError(
+                        ErrorCode::ProtocolViolation,
+                        "SUBSCRIBE_UPDATE moves the end later".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    // The end as sent on the wire: the group plus one, or zero for an open end.
+    fn encode_end(&self) -> Result<FullSequence> {
+        let end_group_object = if let Some(end_group_object) = self.end_group_object.as_ref() {
+            end_group_object
+        } else {
+            return Ok(FullSequence::from((0, 0)));
+        };
+        let end_group_id = if end_group_object.group_id == u64::MAX {
+            if end_group_object.object_id != u64::MAX {
+                return Err(Error::ErrFrameError("Invalid object range".to_string()));
+            }
+            0
+        } else {
+            end_group_object.group_id + 1
+        };
+        let end_object_id = encode_end_object(
+            (end_group_object.object_id != u64::MAX).then_some(end_group_object.object_id),
+        );
+        Ok(FullSequence::from((end_group_id, end_object_id)))
+    }
+
+    fn parameters(&self) -> Result<Option<Parameters>> {
+        let authorization_info = if let Some(authorization_info) = self.authorization_info.as_ref()
+        {
+            authorization_info
+        } else {
+            return Ok(None);
+        };
+        let mut parameters = Parameters::new();
+        parameters.insert(
+            ParameterKey::AuthorizationInfo,
+            authorization_info.to_string(),
+        )?;
+        Ok(Some(parameters))
+    }
+}
+
 impl Deserializer for SubscribeUpdate {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
         let (subscribe_id, sil) = u64::deserialize(r)?;
@@ -31,17 +116,11 @@ impl Deserializer for SubscribeUpdate {
             }
             None
         } else {
-            let end = if end.object_id == 0 {
-                FullSequence {
-                    group_id: end.group_id - 1,
-                    object_id: u64::MAX,
-                }
-            } else {
-                FullSequence {
-                    group_id: end.group_id - 1,
-                    object_id: end.object_id - 1,
-                }
-            };
+            let end: FullSequence = (
+                end.group_id - 1,
+                decode_end_object(end.object_id).unwrap_or(u64::MAX),
+            )
+                .into();
 
             if end.group_id < start.group_id {
                 return Err(Error::ErrParseError(
@@ -59,7 +138,13 @@ impl Deserializer for SubscribeUpdate {
         };
 
         let mut authorization_info: Option<String> = None;
-        let (num_params, mut pl) = u64::deserialize(r)?;
+        let (num_params, mut pl) = usize::deserialize(r)?;
+        if num_params > MAX_PARAMETERS {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "Too many parameters in SUBSCRIBE_UPDATE".to_string(),
+            ));
+        }
         // Parse parameters
         for _ in 0..num_params {
             let (key, kl) = u64::deserialize(r)?;
@@ -71,6 +156,13 @@ impl Deserializer for SubscribeUpdate {
                 return Err(Error::ErrBufferTooShort);
             }
 
+            if !check_parameter(MessageType::SubscribeUpdate, key)? {
+                // Skip unknown parameters.
+                r.advance(size);
+                pl += size;
+                continue;
+            }
+
             if key == ParameterKey::AuthorizationInfo as u64 {
                 if authorization_info.is_some() {
                     return Err(Error::ErrParseError(
@@ -104,45 +196,23 @@ impl Deserializer for SubscribeUpdate {
 impl Serializer for SubscribeUpdate {
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
         let mut l = self.subscribe_id.serialize(w)?;
-
         l += self.start_group_object.serialize(w)?;
-        if let Some(end_group_object) = self.end_group_object.as_ref() {
-            let end_group_id = if end_group_object.group_id == u64::MAX {
-                if end_group_object.object_id != u64::MAX {
-                    return Err(Error::ErrFrameError("Invalid object range".to_string()));
-                }
-                0
-            } else {
-                end_group_object.group_id + 1
-            };
-            let end_object_id = if end_group_object.object_id == u64::MAX {
-                0
-            } else {
-                end_group_object.object_id + 1
-            };
-
-            l += FullSequence {
-                group_id: end_group_id,
-                object_id: end_object_id,
-            }
-            .serialize(w)?;
-        } else {
-            l += FullSequence {
-                group_id: 0,
-                object_id: 0,
-            }
-            .serialize(w)?;
-        }
-
-        if let Some(authorization_info) = self.authorization_info.as_ref() {
-            let mut parameters = Parameters::new();
-            parameters.insert(
-                ParameterKey::AuthorizationInfo,
-                authorization_info.to_string(),
-            )?;
+        l += self.encode_end()?.serialize(w)?;
+        if let Some(parameters) = self.parameters()? {
             l += parameters.serialize(w)?;
         }
+        Ok(l)
+    }
+}
 
+impl SerializedSize for SubscribeUpdate {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.subscribe_id.serialized_size()?;
+        l += self.start_group_object.serialized_size()?;
+        l += self.encode_end()?.serialized_size()?;
+        if let Some(parameters) = self.parameters()? {
+            l += parameters.serialized_size()?;
+        }
         Ok(l)
     }
 }
@@ -185,4 +255,35 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_subscribe_update_is_valid_update() -> Result<()> {
+        let original = Subscribe {
+            filter_type: FilterType::AbsoluteRange((3, 0).into(), (8, 5).into()),
+            ..Default::default()
+        };
+        let mut update = SubscribeUpdate {
+            start_group_object: (4, 0).into(),
+            end_group_object: Some((7, 0).into()),
+            ..Default::default()
+        };
+        update.is_valid_update(&original)?;
+
+        // Widening on either side is illegal.
+        update.start_group_object = (2, 9).into();
+        assert!(update.is_valid_update(&original).is_err());
+        update.start_group_object = (4, 0).into();
+        update.end_group_object = Some((8, 6).into());
+        assert!(update.is_valid_update(&original).is_err());
+        update.end_group_object = None;
+        assert!(update.is_valid_update(&original).is_err());
+
+        // An open-ended subscription may stay open-ended.
+        let original = Subscribe {
+            filter_type: FilterType::AbsoluteStart((3, 0).into()),
+            ..Default::default()
+        };
+        update.is_valid_update(&original)?;
+        Ok(())
+    }
 }
@@ -1,7 +1,12 @@
-use crate::message::FullSequence;
-use crate::{Deserializer, Result, Serializer};
+use crate::message::message_parser::ErrorCode;
+use crate::message::{deserialize_track_namespace, FullSequence};
+use crate::{Deserializer, Error, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum TrackStatusCode {
     #[default]
@@ -12,6 +17,39 @@ pub enum TrackStatusCode {
     StatusNotAvailable = 0x4,
 }
 
+impl TryFrom<u64> for TrackStatusCode {
+    type Error = Error;
+
+    fn try_from(value: u64) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0x0 => Ok(TrackStatusCode::InProgress),
+            0x1 => Ok(TrackStatusCode::DoesNotExist),
+            0x2 => Ok(TrackStatusCode::NotYetBegun),
+            0x3 => Ok(TrackStatusCode::Finished),
+            0x4 => Ok(TrackStatusCode::StatusNotAvailable),
+            _ => Err(Error::ErrOther(format!(
+                "Invalid track status code {}",
+                value
+            ))),
+        }
+    }
+}
+
+impl TrackStatusCode {
+    /// Whether last_group and last_object carry meaningful values. If not,
+    /// both must be zero.
+    pub fn does_imply_having_data(&self) -> bool {
+        matches!(
+            *self,
+            TrackStatusCode::InProgress | TrackStatusCode::Finished
+        )
+    }
+}
+
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct TrackStatus {
     pub track_namespace: String,
@@ -20,26 +58,47 @@ pub struct TrackStatus {
     pub last_group_object: FullSequence,
 }
 
+impl TrackStatus {
+    /// Unknown status codes are not checked.
+    fn has_unexpected_data(&self) -> bool {
+        TrackStatusCode::try_from(self.status_code).is_ok_and(|code| !code.does_imply_having_data())
+            && self.last_group_object != FullSequence::default()
+    }
+    fn check_data(&self) -> Result<()> {
+        if self.has_unexpected_data() {
+            return Err(Error::ErrFrameError(
+                "TRACK_STATUS with no data has non-zero last group or object".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
 impl Deserializer for TrackStatus {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
-        let (track_namespace, tnsl) = String::deserialize(r)?;
+        let (track_namespace, tnsl) = deserialize_track_namespace(r)?;
         let (track_name, tnl) = String::deserialize(r)?;
         let (status_code, scl) = u64::deserialize(r)?;
         let (last_group_object, lgol) = FullSequence::deserialize(r)?;
-        Ok((
-            Self {
-                track_namespace,
-                track_name,
-                status_code,
-                last_group_object,
-            },
-            tnsl + tnl + scl + lgol,
-        ))
+        let track_status = Self {
+            track_namespace,
+            track_name,
+            status_code,
+            last_group_object,
+        };
+        if track_status.has_unexpected_data() {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "TRACK_STATUS with no data has non-zero last group or object".to_string(),
+            ));
+        }
+        Ok((track_status, tnsl + tnl + scl + lgol))
     }
 }
 
 impl Serializer for TrackStatus {
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
+        self.check_data()?;
         let mut l = self.track_namespace.serialize(w)?;
         l += self.track_name.serialize(w)?;
         l += self.status_code.serialize(w)?;
@@ -48,6 +107,17 @@ impl Serializer for TrackStatus {
     }
 }
 
+impl SerializedSize for TrackStatus {
+    fn serialized_size(&self) -> Result<usize> {
+        self.check_data()?;
+        let mut l = self.track_namespace.serialized_size()?;
+        l += self.track_name.serialized_size()?;
+        l += self.status_code.serialized_size()?;
+        l += self.last_group_object.serialized_size()?;
+        Ok(l)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -83,4 +153,58 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_track_status_finished_has_data() -> Result<()> {
+        let expected_packet: Vec<u8> = vec![
+            0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+            0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
+            0x03, 0x0c, 0x14, // status = Finished, last_group, last_object
+        ];
+
+        let expected_message = TrackStatus {
+            track_namespace: "foo".to_string(),
+            track_name: "abcd".to_string(),
+            status_code: TrackStatusCode::Finished as u64,
+            last_group_object: FullSequence::new(12, 20),
+        };
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(expected_packet.as_ref());
+        let (actual_message, _) = TrackStatus::deserialize(&mut cursor)?;
+        assert_eq!(expected_message, actual_message);
+
+        let mut actual_packet = vec![];
+        let _ = expected_message.serialize(&mut actual_packet)?;
+        assert_eq!(expected_packet, actual_packet);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_track_status_not_yet_begun_requires_zeros() -> Result<()> {
+        let mut packet: Vec<u8> = vec![
+            0x03, 0x66, 0x6f, 0x6f, // track_namespace = "foo"
+            0x04, 0x61, 0x62, 0x63, 0x64, // track_name = "abcd"
+            0x02, 0x00, 0x00, // status = NotYetBegun, last_group, last_object
+        ];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(packet.as_ref());
+        let (mut message, _) = TrackStatus::deserialize(&mut cursor)?;
+        assert_eq!(message.last_group_object, FullSequence::default());
+
+        packet[11] = 0x01;
+        let mut cursor: Cursor<&[u8]> = Cursor::new(packet.as_ref());
+        assert!(matches!(
+            TrackStatus::deserialize(&mut cursor),
+            Err(Error::ErrParseError(ErrorCode::ProtocolViolation, _))
+        ));
+
+        message.last_group_object = FullSequence::new(0, 1);
+        let mut actual_packet = vec![];
+        assert!(matches!(
+            message.serialize(&mut actual_packet),
+            Err(Error::ErrFrameError(_))
+        ));
+
+        Ok(())
+    }
 }
@@ -1,6 +1,11 @@
-use crate::{Deserializer, Result, Serializer};
+use crate::message::deserialize_track_namespace;
+use crate::{Deserializer, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct TrackStatusRequest {
     pub track_namespace: String,
@@ -9,7 +14,7 @@ pub struct TrackStatusRequest {
 
 impl Deserializer for TrackStatusRequest {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
-        let (track_namespace, tnsl) = String::deserialize(r)?;
+        let (track_namespace, tnsl) = deserialize_track_namespace(r)?;
         let (track_name, tnl) = String::deserialize(r)?;
         Ok((
             Self {
@@ -29,6 +34,14 @@ impl Serializer for TrackStatusRequest {
     }
 }
 
+impl SerializedSize for TrackStatusRequest {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.track_namespace.serialized_size()?;
+        l += self.track_name.serialized_size()?;
+        Ok(l)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1,6 +1,11 @@
-use crate::{Deserializer, Result, Serializer};
+use crate::message::deserialize_track_namespace;
+use crate::{Deserializer, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct UnAnnounce {
     pub track_namespace: String,
@@ -8,7 +13,7 @@ pub struct UnAnnounce {
 
 impl Deserializer for UnAnnounce {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
-        let (track_namespace, tnsl) = String::deserialize(r)?;
+        let (track_namespace, tnsl) = deserialize_track_namespace(r)?;
         Ok((Self { track_namespace }, tnsl))
     }
 }
@@ -19,6 +24,12 @@ impl Serializer for UnAnnounce {
     }
 }
 
+impl SerializedSize for UnAnnounce {
+    fn serialized_size(&self) -> Result<usize> {
+        self.track_namespace.serialized_size()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1,6 +1,10 @@
-use crate::{Deserializer, Result, Serializer};
+use crate::{Deserializer, Result, SerializedSize, Serializer};
 use bytes::{Buf, BufMut};
 
+#[cfg_attr(
+    feature = "serde-derive",
+    derive(::serde::Serialize, ::serde::Deserialize)
+)]
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct UnSubscribe {
     pub subscribe_id: u64,
@@ -19,6 +23,12 @@ impl Serializer for UnSubscribe {
     }
 }
 
+impl SerializedSize for UnSubscribe {
+    fn serialized_size(&self) -> Result<usize> {
+        self.subscribe_id.serialized_size()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
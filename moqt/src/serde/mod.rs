@@ -15,6 +15,18 @@ pub trait Serializer {
     fn serialize<B: BufMut>(&self, w: &mut B) -> Result<usize>;
 }
 
+/// The number of bytes Serializer::serialize writes, computed without a
+/// buffer. Fails wherever serialize would reject the value itself.
+pub trait SerializedSize {
+    fn serialized_size(&self) -> Result<usize>;
+}
+
+impl SerializedSize for bool {
+    fn serialized_size(&self) -> Result<usize> {
+        Ok(1)
+    }
+}
+
 impl Serializer for bool {
     /// Encode a varint to the given writer.
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
@@ -43,7 +55,7 @@ impl Deserializer for bool {
 impl Serializer for Bytes {
     /// Encode a varint to the given writer.
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
-        if !w.has_remaining_mut() {
+        if w.remaining_mut() < self.len() {
             return Err(Error::ErrBufferTooShort);
         }
         w.put(self.slice(..));
@@ -60,17 +72,23 @@ impl Deserializer for Bytes {
 
 impl Deserializer for String {
     fn deserialize<B: Buf>(r: &mut B) -> Result<(Self, usize)> {
-        let (size, l) = usize::deserialize(r)?;
-        if r.remaining() < size {
-            return Err(Error::ErrBufferTooShort);
-        }
-
-        let mut buf = vec![0; size];
-        r.copy_to_slice(&mut buf);
-        let str = String::from_utf8(buf)?;
+        let (buf, l) = deserialize_length_prefixed(r)?;
+        Ok((String::from_utf8(buf)?, l))
+    }
+}
 
-        Ok((str, size + l))
+// Reads a varint length followed by that many bytes, the encoding shared by
+// the strict and lossy string readers.
+fn deserialize_length_prefixed<R: Buf>(r: &mut R) -> Result<(Vec<u8>, usize)> {
+    let (size, l) = usize::deserialize(r)?;
+    if r.remaining() < size {
+        return Err(Error::ErrBufferTooShort);
     }
+
+    let mut buf = vec![0; size];
+    r.copy_to_slice(&mut buf);
+
+    Ok((buf, size + l))
 }
 
 impl Serializer for String {
@@ -83,3 +101,59 @@ impl Serializer for String {
         Ok(l + self.len())
     }
 }
+
+impl SerializedSize for String {
+    fn serialized_size(&self) -> Result<usize> {
+        Ok(self.len().serialized_size()? + self.len())
+    }
+}
+
+/// Reads a length-prefixed string like the String deserializer, but replaces
+/// invalid UTF-8 with U+FFFD instead of failing. Meant for reason phrases and
+/// URIs that are only displayed; everything else should use the strict path.
+pub fn deserialize_string_lossy<R: Buf>(r: &mut R) -> Result<(String, usize)> {
+    let (buf, l) = deserialize_length_prefixed(r)?;
+    Ok((String::from_utf8_lossy(&buf).into_owned(), l))
+}
+
+/// Takes up to |n| bytes from |r| without interpreting them, e.g. to peek at
+/// the start of an object payload. Returns fewer bytes if fewer remain.
+pub fn read_at_most<R: Buf>(r: &mut R, n: usize) -> Bytes {
+    let n = std::cmp::min(n, r.remaining());
+    r.copy_to_bytes(n)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_string_lossy() -> Result<()> {
+        let mut r = &[0x03u8, 0x66, 0xff, 0x6f][..];
+        assert_eq!(
+            deserialize_string_lossy(&mut r)?,
+            ("f\u{fffd}o".to_string(), 4)
+        );
+
+        let mut r = &[0x03u8, 0x66, 0xff, 0x6f][..];
+        assert!(matches!(
+            String::deserialize(&mut r),
+            Err(Error::ErrInvalidString(_))
+        ));
+
+        let mut r = &[0x03u8, 0x66][..];
+        assert_eq!(
+            deserialize_string_lossy(&mut r),
+            Err(Error::ErrBufferTooShort)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_at_most() {
+        let mut r = &[0x66u8, 0xff, 0x6f][..];
+        assert_eq!(read_at_most(&mut r, 2), Bytes::from_static(&[0x66, 0xff]));
+        assert_eq!(read_at_most(&mut r, 10), Bytes::from_static(&[0x6f]));
+        assert!(read_at_most(&mut r, 10).is_empty());
+    }
+}
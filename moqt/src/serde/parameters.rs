@@ -1,6 +1,8 @@
-use crate::serde::{Deserializer, Serializer};
+use crate::message::message_parser::ErrorCode;
+use crate::message::MessageType;
+use crate::serde::{Deserializer, SerializedSize, Serializer};
 use crate::{Error, Result};
-use bytes::BufMut;
+use bytes::{Buf, BufMut};
 use std::collections::HashMap;
 use std::io::Cursor;
 
@@ -25,6 +27,83 @@ impl TryFrom<u64> for ParameterKey {
     }
 }
 
+impl ParameterKey {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ParameterKey::Role => "ROLE",
+            ParameterKey::Path => "PATH",
+            ParameterKey::AuthorizationInfo => "AUTHORIZATION_INFO",
+        }
+    }
+}
+
+const KNOWN_PARAMETERS: [ParameterKey; 3] = [
+    ParameterKey::Role,
+    ParameterKey::Path,
+    ParameterKey::AuthorizationInfo,
+];
+
+/// The parameters a message may carry. Parsers reject a known parameter that
+/// is not listed for the message; parameters unknown to this draft are skipped.
+pub fn allowed_parameters(message_type: MessageType) -> &'static [ParameterKey] {
+    match message_type {
+        MessageType::ClientSetup => &[ParameterKey::Role, ParameterKey::Path],
+        MessageType::ServerSetup => &[ParameterKey::Role],
+        MessageType::Subscribe | MessageType::SubscribeUpdate | MessageType::Announce => {
+            &[ParameterKey::AuthorizationInfo]
+        }
+        _ => &[],
+    }
+}
+
+/// Checks a parameter key read from |message_type| against
+/// allowed_parameters. Returns true if the parameter should be parsed, false
+/// if it is unknown and should be skipped.
+pub(crate) fn check_parameter(message_type: MessageType, key: u64) -> Result<bool> {
+    if allowed_parameters(message_type)
+        .iter()
+        .any(|allowed| *allowed as u64 == key)
+    {
+        return Ok(true);
+    }
+    if let Some(known) = KNOWN_PARAMETERS.iter().find(|known| **known as u64 == key) {
+        return Err(Error::ErrParseError(
+            ErrorCode::ProtocolViolation,
+            format!(
+                "{} parameter is not allowed in {}",
+                known.name(),
+                message_type.name()
+            ),
+        ));
+    }
+    Ok(false)
+}
+
+/// Reads the integer parameter |key| whose value takes |size| bytes. The
+/// value must be a single varint filling exactly that many bytes.
+pub(crate) fn deserialize_int_parameter<R: Buf>(
+    r: &mut R,
+    size: usize,
+    key: ParameterKey,
+) -> Result<(u64, usize)> {
+    let mut value = r.take(size);
+    let result = u64::deserialize(&mut value);
+    let remaining = value.remaining();
+    match result {
+        Ok((v, vl)) if remaining == 0 => Ok((v, vl)),
+        _ => Err(Error::ErrParseError(
+            ErrorCode::ParameterLengthMismatch,
+            format!(
+                "{} parameter length does not match varint encoding",
+                key.name()
+            ),
+        )),
+    }
+}
+
+/// Parameters keyed by type. Whatever the insertion order, they are always
+/// serialized in ascending key order, so every message writes them the same
+/// way and the count prefix matches the entries written.
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct Parameters(pub HashMap<u64, Vec<u8>>);
 
@@ -32,9 +111,8 @@ impl Serializer for Parameters {
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
         let mut l = self.0.len().serialize(w)?;
 
-        #[allow(clippy::map_clone)]
-        let mut kinds: Vec<u64> = self.0.keys().map(|key| *key).collect();
-        kinds.sort();
+        let mut kinds: Vec<u64> = self.0.keys().copied().collect();
+        kinds.sort_unstable();
         for kind in kinds {
             l += kind.serialize(w)?;
             let value = &self.0[&kind];
@@ -54,6 +132,22 @@ impl Serializer for Parameters {
     }
 }
 
+impl SerializedSize for Parameters {
+    fn serialized_size(&self) -> Result<usize> {
+        let mut l = self.0.len().serialized_size()?;
+        for (kind, value) in self.0.iter() {
+            l += kind.serialized_size()?;
+            if !(*kind == ParameterKey::Path as u64
+                || *kind == ParameterKey::AuthorizationInfo as u64)
+            {
+                l += value.len().serialized_size()?;
+            }
+            l += value.len();
+        }
+        Ok(l)
+    }
+}
+
 impl Parameters {
     pub fn new() -> Self {
         Self::default()
@@ -117,4 +211,111 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_serialize_params() -> Result<()> {
+        let mut params = Parameters::new();
+        params.insert(ParameterKey::AuthorizationInfo, "password".to_string())?;
+        params.insert(ParameterKey::Path, "/a".to_string())?;
+        params.insert(ParameterKey::Role, Role::PubSub)?;
+
+        let mut w = vec![];
+        let l = params.serialize(&mut w)?;
+        let expected = vec![
+            0x03, // 3 params
+            0x00, 0x01, 0x03, // role = PubSub
+            0x01, 0x02, 0x2f, 0x61, // path = "/a"
+            0x02, 0x08, 0x70, 0x61, 0x73, 0x73, 0x77, 0x6f, 0x72, 0x64, // auth = "password"
+        ];
+        assert_eq!(l, expected.len());
+        assert_eq!(w, expected);
+
+        let mut short = [0u8; 10];
+        assert_eq!(
+            params.serialize(&mut &mut short[..]),
+            Err(Error::ErrBufferTooShort)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_params_ascending() -> Result<()> {
+        let mut params = Parameters::new();
+        for key in [0x40u64, 0x21, 0x00, 0x3fff] {
+            params.0.insert(key, vec![]);
+        }
+        params.insert(ParameterKey::AuthorizationInfo, String::new())?;
+        params.insert(ParameterKey::Path, String::new())?;
+
+        let mut w = vec![];
+        params.serialize(&mut w)?;
+        let mut r = &w[..];
+        let (count, _) = u64::deserialize(&mut r)?;
+        assert_eq!(count, 6);
+        let mut keys = vec![];
+        while r.has_remaining() {
+            let (key, _) = u64::deserialize(&mut r)?;
+            // Path and AuthorizationInfo values carry their own length.
+            let (len, _) = usize::deserialize(&mut r)?;
+            r.advance(len);
+            keys.push(key);
+        }
+        assert_eq!(keys, vec![0x00, 0x01, 0x02, 0x21, 0x40, 0x3fff]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_int_parameter() -> Result<()> {
+        let mut r = &[0x03u8, 0xff][..];
+        assert_eq!(
+            deserialize_int_parameter(&mut r, 1, ParameterKey::Role)?,
+            (3, 1)
+        );
+        assert_eq!(r, &[0xff]);
+
+        let mut r = &[0x40u8, 0x03][..];
+        assert_eq!(
+            deserialize_int_parameter(&mut r, 2, ParameterKey::Role)?,
+            (3, 2)
+        );
+
+        // Declared length longer than the varint.
+        let mut r = &[0x03u8, 0x00][..];
+        assert!(matches!(
+            deserialize_int_parameter(&mut r, 2, ParameterKey::Role),
+            Err(Error::ErrParseError(ErrorCode::ParameterLengthMismatch, _))
+        ));
+
+        // Declared length shorter than the varint.
+        let mut r = &[0x40u8, 0x03][..];
+        assert!(matches!(
+            deserialize_int_parameter(&mut r, 1, ParameterKey::Role),
+            Err(Error::ErrParseError(ErrorCode::ParameterLengthMismatch, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_allowed_parameters() -> Result<()> {
+        assert_eq!(
+            allowed_parameters(MessageType::Announce),
+            &[ParameterKey::AuthorizationInfo]
+        );
+        assert!(!allowed_parameters(MessageType::Announce).contains(&ParameterKey::Role));
+        assert!(allowed_parameters(MessageType::SubscribeOk).is_empty());
+
+        assert!(check_parameter(
+            MessageType::ClientSetup,
+            ParameterKey::Path as u64
+        )?);
+        assert!(!check_parameter(MessageType::ClientSetup, 0x20)?);
+        assert_eq!(
+            check_parameter(MessageType::ServerSetup, ParameterKey::Path as u64),
+            Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                "PATH parameter is not allowed in SERVER_SETUP".to_string()
+            ))
+        );
+        Ok(())
+    }
 }
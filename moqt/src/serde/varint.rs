@@ -1,4 +1,4 @@
-use crate::serde::{Deserializer, Serializer};
+use crate::serde::{Deserializer, SerializedSize, Serializer};
 use crate::{Error, Result};
 use bytes::{Buf, BufMut};
 use std::fmt;
@@ -45,6 +45,31 @@ impl VarInt {
         self.0
     }
 
+    /// Decode a varint if all of its bytes are available. Returns `Ok(None)`
+    /// without consuming anything when more bytes are needed.
+    pub fn try_deserialize<B: Buf>(r: &mut B) -> Result<Option<(Self, usize)>> {
+        if !r.has_remaining() {
+            return Ok(None);
+        }
+        let size = 1usize << (r.chunk()[0] >> 6);
+        if r.remaining() < size {
+            return Ok(None);
+        }
+        Self::deserialize(r).map(Some)
+    }
+
+    /// Decode the next varint without consuming it. Only the first chunk of
+    /// |r| is examined, so this expects a contiguous buffer such as `BytesMut`
+    /// or a slice; returns `None` if the varint is not entirely in that chunk.
+    pub fn peek<B: Buf>(r: &B) -> Option<(Self, usize)> {
+        let chunk = r.chunk();
+        let size = 1usize << (chunk.first()? >> 6);
+        if chunk.len() < size {
+            return None;
+        }
+        Self::deserialize(&mut &chunk[..size]).ok()
+    }
+
     /// Compute the number of bytes needed to encode this value
     pub fn size(self) -> usize {
         let x = self.0;
@@ -199,6 +224,12 @@ impl Serializer for VarInt {
     }
 }
 
+impl SerializedSize for VarInt {
+    fn serialized_size(&self) -> Result<usize> {
+        Ok(self.size())
+    }
+}
+
 impl Serializer for u64 {
     /// Encode a varint to the given writer.
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
@@ -213,6 +244,12 @@ impl Deserializer for u64 {
     }
 }
 
+impl SerializedSize for u64 {
+    fn serialized_size(&self) -> Result<usize> {
+        VarInt::try_from(*self)?.serialized_size()
+    }
+}
+
 impl Serializer for usize {
     /// Encode a varint to the given writer.
     fn serialize<W: BufMut>(&self, w: &mut W) -> Result<usize> {
@@ -221,8 +258,85 @@ impl Serializer for usize {
     }
 }
 
+impl SerializedSize for usize {
+    fn serialized_size(&self) -> Result<usize> {
+        VarInt::try_from(*self)?.serialized_size()
+    }
+}
+
 impl Deserializer for usize {
     fn deserialize<R: Buf>(r: &mut R) -> Result<(Self, usize)> {
         VarInt::deserialize(r).map(|v| (v.0.into_inner() as usize, v.1))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_peek() -> Result<()> {
+        // 494878333 as a 4-byte varint, followed by another byte.
+        let buf = [0x9du8, 0x7f, 0x3e, 0x7d, 0x25];
+        let mut r = &buf[..];
+        assert_eq!(VarInt::peek(&r), Some((VarInt(494878333), 4)));
+        assert_eq!(r.remaining(), 5);
+        assert_eq!(VarInt::deserialize(&mut r)?, (VarInt(494878333), 4));
+        assert_eq!(VarInt::peek(&r), Some((VarInt(0x25), 1)));
+
+        assert_eq!(VarInt::peek(&&buf[..3]), None);
+        assert_eq!(VarInt::peek(&&buf[..0]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_deserialize_resumes() -> Result<()> {
+        // 494878333 as a 4-byte varint.
+        let encoded = [0x9d, 0x7f, 0x3e, 0x7d];
+
+        let mut r = &encoded[..1];
+        assert_eq!(VarInt::try_deserialize(&mut r)?, None);
+        assert_eq!(r.remaining(), 1);
+
+        let mut r = &encoded[..3];
+        assert_eq!(VarInt::try_deserialize(&mut r)?, None);
+        assert_eq!(r.remaining(), 3);
+
+        let mut r = &encoded[..];
+        assert_eq!(
+            VarInt::try_deserialize(&mut r)?,
+            Some((VarInt::from_u32(494878333), 4))
+        );
+        assert!(!r.has_remaining());
+
+        let mut r = &[][..];
+        assert_eq!(VarInt::try_deserialize(&mut r)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_truncated() -> Result<()> {
+        // Every prefix of each encoding length is reported as an unexpected
+        // end, i.e. more bytes could still arrive, not as corrupt data.
+        let encodings: [&[u8]; 3] = [
+            &[0x7b, 0xbd],
+            &[0x9d, 0x7f, 0x3e, 0x7d],
+            &[0xc2, 0x19, 0x7c, 0x5e, 0xff, 0x14, 0xe8, 0x8c],
+        ];
+        for encoded in encodings {
+            for len in 0..encoded.len() {
+                let mut r = &encoded[..len];
+                assert!(
+                    matches!(VarInt::deserialize(&mut r), Err(Error::ErrUnexpectedEnd)),
+                    "{} of {} bytes",
+                    len,
+                    encoded.len()
+                );
+            }
+            let mut r = encoded;
+            let (_, l) = VarInt::deserialize(&mut r)?;
+            assert_eq!(l, encoded.len());
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,6 @@
+pub use crate::message::Perspective;
 use crate::message::Version;
 
-#[derive(Default, Debug, Copy, Clone, Eq, PartialEq)]
-pub enum Perspective {
-    #[default]
-    Server,
-    Client,
-}
-
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct Config {
     pub version: Version,
@@ -14,4 +8,6 @@ pub struct Config {
     pub use_web_transport: bool,
     pub path: String,
     pub deliver_partial_objects: bool,
+    pub reject_empty_auth_info: bool,
+    pub validate_go_away_uri: bool,
 }
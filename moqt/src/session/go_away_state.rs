@@ -0,0 +1,55 @@
+use crate::message::go_away::GoAway;
+use std::time::{Duration, Instant};
+
+/// Tracks a received GOAWAY. The session must be closed by |deadline|, after
+/// which the peer may terminate it with GoawayTimeout.
+#[derive(Debug, Clone)]
+pub struct GoAwayState {
+    new_session_uri: String,
+    deadline: Instant,
+}
+
+impl GoAwayState {
+    /// |received| is when the GOAWAY arrived and |grace_period| how long the
+    /// session may keep running afterwards.
+    pub fn new(go_away: &GoAway, received: Instant, grace_period: Duration) -> Self {
+        Self {
+            new_session_uri: go_away.new_session_uri.clone(),
+            deadline: received + grace_period,
+        }
+    }
+
+    /// The URI to reconnect to, or empty to reuse the current one.
+    pub fn new_session_uri(&self) -> &str {
+        &self.new_session_uri
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.deadline
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_go_away_deadline() {
+        let received = Instant::now();
+        let go_away = GoAway {
+            new_session_uri: "https://example.com/moq".to_string(),
+        };
+        let state = GoAwayState::new(&go_away, received, Duration::from_secs(10));
+        assert_eq!(state.new_session_uri(), "https://example.com/moq");
+        assert_eq!(state.deadline(), received + Duration::from_secs(10));
+
+        assert!(!state.is_expired(received));
+        assert!(!state.is_expired(state.deadline() - Duration::from_nanos(1)));
+        assert!(state.is_expired(state.deadline()));
+        assert!(state.is_expired(state.deadline() + Duration::from_secs(1)));
+    }
+}
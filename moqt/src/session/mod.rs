@@ -9,18 +9,23 @@ use crate::session::config::{Config, Perspective};
 use crate::session::local_track::LocalTrack;
 use crate::session::remote_track::RemoteTrack;
 use crate::session::stream::{Stream, StreamState};
+use crate::session::track_alias_table::TrackAliasTable;
 use crate::StreamId;
 use crate::{Error, Result};
 use log::info;
 use retty::transport::Transmit;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::time::Instant;
 
 mod config;
+mod go_away_state;
 mod local_track;
 mod remote_track;
+mod send_queue;
 mod stream;
 mod subscribe_window;
+mod track;
+mod track_alias_table;
 
 // If |error_message| is none, the ANNOUNCE was successful.
 pub type OutgoingAnnounceCallback = fn(track_namespace: String, error: Option<AnnounceErrorReason>);
@@ -54,7 +59,7 @@ pub struct Session {
     local_tracks: HashMap<FullTrackName, LocalTrack>,
     local_track_by_subscribe_id: HashMap<u64, FullTrackName>,
     // This is only used to check for track_alias collisions.
-    used_track_aliases: HashSet<u64>,
+    used_track_aliases: TrackAliasTable,
     next_local_track_alias: u64,
 
     // Outgoing SUBSCRIBEs that have not received SUBSCRIBE_OK or SUBSCRIBE_ERROR.
@@ -111,8 +116,19 @@ impl Session {
     }
 
     fn send_control_message(&mut self, control_message: ControlMessage) -> Result<()> {
+        // A SUBSCRIBE_DONE or SUBSCRIBE_ERROR ends the peer's subscription and
+        // frees its alias.
+        let done_subscribe_id = match &control_message {
+            ControlMessage::SubscribeDone(subscribe_done) => Some(subscribe_done.subscribe_id),
+            ControlMessage::SubscribeError(subscribe_error) => Some(subscribe_error.subscribe_id),
+            _ => None,
+        };
         let mut control_stream = self.get_control_stream()?;
-        control_stream.send_control_message(control_message)
+        control_stream.send_control_message(control_message)?;
+        if let Some(subscribe_id) = done_subscribe_id {
+            self.used_track_aliases.remove(subscribe_id);
+        }
+        Ok(())
     }
 }
 
@@ -0,0 +1,87 @@
+use crate::message::object::SendPriority;
+use crate::message::FullSequence;
+use bytes::Bytes;
+use std::collections::BTreeMap;
+
+/// Orders outgoing objects for transmission: by send priority first, then by
+/// ascending sequence. Objects queued twice under the same priority and
+/// sequence are sent in the order they were pushed.
+#[derive(Default, Debug)]
+pub struct SendQueue {
+    entries: BTreeMap<(SendPriority, (u64, u64), u64), Bytes>,
+    next_insertion: u64,
+}
+
+impl SendQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, priority: SendPriority, sequence: FullSequence, payload: Bytes) {
+        self.entries.insert(
+            (priority, sequence.as_group_object(), self.next_insertion),
+            payload,
+        );
+        self.next_insertion += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<(SendPriority, FullSequence, Bytes)> {
+        self.entries
+            .pop_first()
+            .map(|((priority, sequence, _), payload)| (priority, sequence.into(), payload))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Result;
+
+    #[test]
+    fn test_send_queue_interleaved_priorities() -> Result<()> {
+        let high = SendPriority::new(0x10, 2);
+        let low = SendPriority::new(0xf0, 1);
+        let mut queue = SendQueue::new();
+        queue.push(low, FullSequence::new(1, 1), Bytes::from_static(b"l1"));
+        queue.push(high, FullSequence::new(2, 1), Bytes::from_static(b"h1"));
+        queue.push(low, FullSequence::new(1, 0), Bytes::from_static(b"l0"));
+        queue.push(high, FullSequence::new(2, 0), Bytes::from_static(b"h0"));
+        assert_eq!(queue.len(), 4);
+
+        let mut order = vec![];
+        while let Some((priority, sequence, payload)) = queue.pop() {
+            order.push((priority, sequence, payload));
+        }
+        assert!(queue.is_empty());
+        assert_eq!(
+            order,
+            vec![
+                (high, FullSequence::new(2, 0), Bytes::from_static(b"h0")),
+                (high, FullSequence::new(2, 1), Bytes::from_static(b"h1")),
+                (low, FullSequence::new(1, 0), Bytes::from_static(b"l0")),
+                (low, FullSequence::new(1, 1), Bytes::from_static(b"l1")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_queue_same_sequence_is_fifo() -> Result<()> {
+        let priority = SendPriority::new(0, 0);
+        let mut queue = SendQueue::new();
+        queue.push(priority, FullSequence::new(0, 0), Bytes::from_static(b"a"));
+        queue.push(priority, FullSequence::new(0, 0), Bytes::from_static(b"b"));
+        assert_eq!(queue.pop().map(|e| e.2), Some(Bytes::from_static(b"a")));
+        assert_eq!(queue.pop().map(|e| e.2), Some(Bytes::from_static(b"b")));
+        assert!(queue.pop().is_none());
+        Ok(())
+    }
+}
@@ -23,7 +23,7 @@ use crate::session::config::{Config, Perspective};
 use crate::session::remote_track::RemoteTrackOnObjectFragment;
 use crate::session::Session;
 use crate::{Error, Result, StreamId};
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::{Bytes, BytesMut};
 use log::{info, trace};
 use retty::transport::{Transmit, TransportContext};
 use std::collections::VecDeque;
@@ -55,8 +55,11 @@ pub(super) struct StreamState {
     stream_id: StreamId,
     is_control_stream: Option<bool>,
     transport: TransportContext,
-    partial_object: Option<BytesMut>,
     parser: MessageParser,
+    framer: MessageFramer,
+    // Control messages are serialized here and split off, so one allocation
+    // is shared by several messages.
+    write_buffer: BytesMut,
 
     eouts: VecDeque<StreamEventOut>,
     routs: VecDeque<Transmit<StreamMessage>>,
@@ -70,13 +73,18 @@ impl StreamState {
         is_control_stream: Option<bool>,
         transport: TransportContext,
     ) -> Self {
+        let mut parser = MessageParser::new(config.use_web_transport);
+        parser.set_deliver_partial_objects(config.deliver_partial_objects);
+        parser.set_reject_empty_auth_info(config.reject_empty_auth_info);
+        parser.set_validate_go_away_uri(config.validate_go_away_uri);
         Self {
-            parser: MessageParser::new(config.use_web_transport),
+            parser,
+            framer: MessageFramer::new(config.perspective),
+            write_buffer: BytesMut::new(),
             config,
             stream_id,
             is_control_stream,
             transport,
-            partial_object: None,
 
             eouts: VecDeque::new(),
             routs: VecDeque::new(),
@@ -107,8 +115,10 @@ impl StreamState {
     }
 
     fn send_control_message(&mut self, control_message: ControlMessage) -> Result<()> {
-        let mut message = BytesMut::new();
-        let _ = MessageFramer::serialize_control_message(control_message, &mut message)?;
+        let _ = self
+            .framer
+            .serialize_control_to_bytes(control_message, &mut self.write_buffer)?;
+        let message = self.write_buffer.split();
         self.wouts.push_back(Transmit {
             now: Instant::now(),
             transport: self.transport,
@@ -120,7 +130,7 @@ impl StreamState {
     fn on_object_message(
         &mut self,
         object_header: ObjectHeader,
-        mut payload: Bytes,
+        payload: Bytes,
         fin: bool,
     ) -> Result<()> {
         if let Some(&is_control_stream) = self.is_control_stream.as_ref() {
@@ -133,7 +143,7 @@ impl StreamState {
         }
         trace!(
             "{}",
-            format!(
+            format_args!(
                 "{:?} Received OBJECT message on stream {} for subscribe_id {} for
            track alias {} with sequence {}:{} send_order {} forwarding_preference {:?} length {}
            explicit length {} {}",
@@ -155,23 +165,6 @@ impl StreamState {
             )
         );
 
-        if !self.config.deliver_partial_objects {
-            if !fin {
-                // Buffer partial object.
-                if self.partial_object.is_none() {
-                    self.partial_object = Some(BytesMut::new());
-                }
-                if let Some(partial_object) = self.partial_object.as_mut() {
-                    partial_object.put(payload);
-                }
-                return Ok(());
-            }
-            if let Some(mut partial_object) = self.partial_object.take() {
-                // Completes the object
-                partial_object.put(payload);
-                payload = partial_object.freeze();
-            }
-        }
         self.eouts
             .push_back(StreamEventOut::RemoteTrackOnObjectFragment(
                 RemoteTrackOnObjectFragment {
@@ -216,16 +209,7 @@ impl StreamState {
                 supported_version: self.config.version,
                 role: Some(Role::PubSub),
             };
-            let mut message = BytesMut::new();
-            MessageFramer::serialize_control_message(
-                ControlMessage::ServerSetup(response),
-                &mut message,
-            )?;
-            self.wouts.push_back(Transmit {
-                now: Instant::now(),
-                transport: self.transport,
-                message: StreamMessage { message, fin: true },
-            });
+            self.send_control_message(ControlMessage::ServerSetup(response))?;
             info!("{:?} Sent the SERVER_SETUP message", self.perspective());
         }
         self.eouts.push_back(StreamEventOut::SessionEstablished(
@@ -556,7 +540,13 @@ impl Handler for Stream<'_> {
                         stream_state.on_subscribe_update_message(subscribe_update)
                     }
                     ControlMessage::Subscribe(subscribe) => {
-                        stream_state.on_subscribe_message(subscribe)
+                        let (track_alias, subscribe_id) =
+                            (subscribe.track_alias, subscribe.subscribe_id);
+                        stream_state.on_subscribe_message(subscribe)?;
+                        // Only an accepted subscription holds its alias.
+                        self.session
+                            .used_track_aliases
+                            .insert(track_alias, subscribe_id)
                     }
                     ControlMessage::SubscribeOk(subscribe_ok) => {
                         stream_state.on_subscribe_ok_message(subscribe_ok)
@@ -577,7 +567,10 @@ impl Handler for Stream<'_> {
                         stream_state.on_unannounce_message(unannounce)
                     }
                     ControlMessage::UnSubscribe(unsubscribe) => {
-                        stream_state.on_unsubscribe_message(unsubscribe)
+                        let subscribe_id = unsubscribe.subscribe_id;
+                        stream_state.on_unsubscribe_message(unsubscribe)?;
+                        self.session.used_track_aliases.remove(subscribe_id);
+                        Ok(())
                     }
                     ControlMessage::SubscribeDone(subscribe_done) => {
                         stream_state.on_subscribe_done_message(subscribe_done)
@@ -619,3 +612,66 @@ impl Handler for Stream<'_> {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::connection::Connection;
+    use crate::message::FilterType;
+
+    fn stream_state(stream_id: StreamId, is_control_stream: bool) -> StreamState {
+        StreamState::new(
+            Config::default(),
+            stream_id,
+            Some(is_control_stream),
+            TransportContext::default(),
+        )
+    }
+
+    fn receive_subscribe(
+        session: &mut Session,
+        stream_id: StreamId,
+        subscribe_id: u64,
+        track_alias: u64,
+    ) -> Result<()> {
+        let subscribe = Subscribe {
+            subscribe_id,
+            track_alias,
+            track_namespace: "foo".to_string(),
+            track_name: "abcd".to_string(),
+            filter_type: FilterType::LatestGroup,
+            authorization_info: None,
+        };
+        session
+            .stream(stream_id)?
+            .handle_event(StreamEventIn::MessageParserEvent(
+                MessageParserEvent::ControlMessage(ControlMessage::Subscribe(subscribe)),
+            ))
+    }
+
+    #[test]
+    fn test_alias_reused_after_rejected_subscribe() -> Result<()> {
+        let mut session = Session::new(Config::default(), Connection::QUIC);
+        session.streams.insert(0, stream_state(0, true));
+        session.streams.insert(1, stream_state(1, false));
+        session.control_stream_id = Some(0);
+
+        // A SUBSCRIBE the handler rejects never holds its alias.
+        assert!(receive_subscribe(&mut session, 1, 1, 4).is_err());
+        assert!(!session.used_track_aliases.contains(4));
+        receive_subscribe(&mut session, 0, 2, 4)?;
+        assert_eq!(session.used_track_aliases.subscribe_id(4), Some(2));
+
+        // Answering with SUBSCRIBE_ERROR frees it again.
+        session.send_control_message(ControlMessage::SubscribeError(SubscribeError {
+            subscribe_id: 2,
+            error_code: 0,
+            reason_phrase: String::new(),
+            track_alias: 4,
+        }))?;
+        assert!(!session.used_track_aliases.contains(4));
+        receive_subscribe(&mut session, 0, 3, 4)?;
+        assert_eq!(session.used_track_aliases.subscribe_id(4), Some(3));
+        Ok(())
+    }
+}
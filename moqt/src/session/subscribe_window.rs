@@ -331,9 +331,9 @@ mod test {
             Some(2).as_ref()
         );
         window.remove_stream(7, 2);
-        assert!(!window
+        assert!(window
             .get_stream_for_sequence(FullSequence::new(4, 0))
-            .is_some());
+            .is_none());
         Ok(())
     }
 
@@ -348,9 +348,9 @@ mod test {
             Some(test.end),
         );
         assert!(window.add_stream(4, 0, 2).is_ok());
-        assert!(!window
+        assert!(window
             .get_stream_for_sequence(FullSequence::new(5, 0))
-            .is_some());
+            .is_none());
         assert!(window.add_stream(5, 2, 6).is_ok());
         assert_eq!(
             Error::ErrOther("Stream already added".to_string()),
@@ -365,9 +365,9 @@ mod test {
             Some(6).as_ref()
         );
         window.remove_stream(5, 1);
-        assert!(!window
+        assert!(window
             .get_stream_for_sequence(FullSequence::new(5, 2))
-            .is_some());
+            .is_none());
         Ok(())
     }
 
@@ -396,16 +396,16 @@ mod test {
             window.get_stream_for_sequence(FullSequence::new(4, 2)),
             Some(10).as_ref()
         );
-        assert!(!window
+        assert!(window
             .get_stream_for_sequence(FullSequence::new(4, 4))
-            .is_some());
-        assert!(!window
+            .is_none());
+        assert!(window
             .get_stream_for_sequence(FullSequence::new(5, 0))
-            .is_some());
+            .is_none());
         window.remove_stream(4, 2);
-        assert!(!window
+        assert!(window
             .get_stream_for_sequence(FullSequence::new(4, 2))
-            .is_some());
+            .is_none());
         Ok(())
     }
 
@@ -436,7 +436,7 @@ mod test {
             test.start,
             Some(test.end),
         );
-        assert!(!window.largest_delivered().is_some());
+        assert!(window.largest_delivered().is_none());
         assert!(!window.on_object_sent(FullSequence::new(4, 1), ObjectStatus::Normal));
         assert!(window.largest_delivered().is_some());
         assert_eq!(window.largest_delivered().unwrap(), FullSequence::new(4, 1));
@@ -0,0 +1,187 @@
+use crate::message::message_parser::ErrorCode;
+use crate::message::subscribe::Subscribe;
+use crate::message::subscribe_ok::SubscribeOk;
+use crate::message::subscribe_update::SubscribeUpdate;
+use crate::message::{FilterType, FullSequence, FullTrackName};
+use crate::{Error, Result};
+
+/// The state of one subscription to a track: its name, the identifiers the
+/// SUBSCRIBE assigned, the current filter and the largest object known to
+/// exist. Built from the SUBSCRIBE and updated by SUBSCRIBE_OK and
+/// SUBSCRIBE_UPDATE.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Track {
+    full_track_name: FullTrackName,
+    track_alias: u64,
+    subscribe_id: u64,
+    filter_type: FilterType,
+    largest: Option<FullSequence>,
+}
+
+impl Track {
+    pub fn new(subscribe: &Subscribe) -> Self {
+        Self {
+            full_track_name: FullTrackName::new(
+                subscribe.track_namespace.clone(),
+                subscribe.track_name.clone(),
+            ),
+            track_alias: subscribe.track_alias,
+            subscribe_id: subscribe.subscribe_id,
+            filter_type: subscribe.filter_type,
+            largest: None,
+        }
+    }
+
+    pub fn full_track_name(&self) -> &FullTrackName {
+        &self.full_track_name
+    }
+
+    pub fn track_alias(&self) -> u64 {
+        self.track_alias
+    }
+
+    pub fn subscribe_id(&self) -> u64 {
+        self.subscribe_id
+    }
+
+    pub fn filter_type(&self) -> &FilterType {
+        &self.filter_type
+    }
+
+    pub fn largest(&self) -> Option<FullSequence> {
+        self.largest
+    }
+
+    /// Records the largest object reported by SUBSCRIBE_OK.
+    pub fn on_subscribe_ok(&mut self, subscribe_ok: &SubscribeOk) -> Result<()> {
+        self.check_subscribe_id("SUBSCRIBE_OK", subscribe_ok.subscribe_id)?;
+        self.largest = subscribe_ok.largest_group_object;
+        Ok(())
+    }
+
+    /// Narrows the filter to the range in |subscribe_update|. Fails without
+    /// changing the track if the update would widen the range.
+    pub fn on_subscribe_update(&mut self, subscribe_update: &SubscribeUpdate) -> Result<()> {
+        self.check_subscribe_id("SUBSCRIBE_UPDATE", subscribe_update.subscribe_id)?;
+        subscribe_update.is_valid_filter_update(&self.filter_type)?;
+        let start = subscribe_update.start_group_object;
+        self.filter_type = match subscribe_update.end_group_object {
+            Some(end) => FilterType::AbsoluteRange(start, end),
+            None => FilterType::AbsoluteStart(start),
+        };
+        Ok(())
+    }
+
+    /// Returns true if |sequence| falls within the current filter. Relative
+    /// filters accept everything, since their start is fixed by the publisher.
+    pub fn in_range(&self, sequence: FullSequence) -> bool {
+        match self.filter_type.object_range() {
+            None => true,
+            Some((start, end)) => sequence >= start && end.is_none_or(|end| sequence <= end),
+        }
+    }
+
+    fn check_subscribe_id(&self, message: &str, subscribe_id: u64) -> Result<()> {
+        if subscribe_id != self.subscribe_id {
+            return Err(Error::ErrParseError(
+                ErrorCode::ProtocolViolation,
+                format!(
+                    "{} for subscribe_id {} applied to track with subscribe_id {}",
+                    message, subscribe_id, self.subscribe_id
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn subscribe(filter_type: FilterType) -> Subscribe {
+        Subscribe {
+            subscribe_id: 1,
+            track_alias: 2,
+            track_namespace: "foo".to_string(),
+            track_name: "abcd".to_string(),
+            filter_type,
+            authorization_info: None,
+        }
+    }
+
+    #[test]
+    fn test_subscribe_ok_then_update() -> Result<()> {
+        let mut track = Track::new(&subscribe(FilterType::AbsoluteStart((4, 0).into())));
+        assert_eq!(
+            track.full_track_name(),
+            &FullTrackName::new("foo".to_string(), "abcd".to_string())
+        );
+        assert_eq!(track.track_alias(), 2);
+        assert_eq!(track.subscribe_id(), 1);
+        assert_eq!(track.largest(), None);
+        assert!(track.in_range((9, 9).into()));
+
+        track.on_subscribe_ok(&SubscribeOk {
+            subscribe_id: 1,
+            expires: 0,
+            largest_group_object: Some((5, 3).into()),
+        })?;
+        assert_eq!(track.largest(), Some((5, 3).into()));
+
+        track.on_subscribe_update(&SubscribeUpdate {
+            subscribe_id: 1,
+            start_group_object: (5, 0).into(),
+            end_group_object: Some((7, u64::MAX).into()),
+            authorization_info: None,
+        })?;
+        assert_eq!(
+            track.filter_type(),
+            &FilterType::AbsoluteRange((5, 0).into(), (7, u64::MAX).into())
+        );
+        assert!(!track.in_range((4, 9).into()));
+        assert!(track.in_range((7, 100).into()));
+        assert!(!track.in_range((8, 0).into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_cannot_widen() -> Result<()> {
+        let filter_type = FilterType::AbsoluteRange((3, 0).into(), (8, 5).into());
+        let mut track = Track::new(&subscribe(filter_type));
+
+        let earlier_start = SubscribeUpdate {
+            subscribe_id: 1,
+            start_group_object: (2, 0).into(),
+            end_group_object: Some((8, 5).into()),
+            authorization_info: None,
+        };
+        assert!(track.on_subscribe_update(&earlier_start).is_err());
+
+        let open_end = SubscribeUpdate {
+            subscribe_id: 1,
+            start_group_object: (3, 0).into(),
+            end_group_object: None,
+            authorization_info: None,
+        };
+        assert!(track.on_subscribe_update(&open_end).is_err());
+        assert_eq!(track.filter_type(), &filter_type);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_subscribe_id() -> Result<()> {
+        let mut track = Track::new(&subscribe(FilterType::LatestGroup));
+        let result = track.on_subscribe_ok(&SubscribeOk {
+            subscribe_id: 3,
+            expires: 0,
+            largest_group_object: Some((5, 3).into()),
+        });
+        assert!(matches!(
+            result,
+            Err(Error::ErrParseError(ErrorCode::ProtocolViolation, _))
+        ));
+        assert_eq!(track.largest(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use crate::message::message_parser::ErrorCode;
+use crate::{Error, Result};
+use std::collections::HashMap;
+
+/// Maps the track aliases of active incoming SUBSCRIBEs to their subscribe
+/// IDs, so that a SUBSCRIBE reusing an alias still in use can be rejected.
+/// An alias is freed when its subscription ends.
+#[derive(Default, Debug)]
+pub struct TrackAliasTable {
+    alias_to_subscribe_id: HashMap<u64, u64>,
+    subscribe_id_to_alias: HashMap<u64, u64>,
+}
+
+impl TrackAliasTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records |track_alias| for |subscribe_id|. Fails with
+    /// DuplicateTrackAlias if another active subscription holds the alias, and
+    /// with ProtocolViolation if |subscribe_id| already holds another alias.
+    pub fn insert(&mut self, track_alias: u64, subscribe_id: u64) -> Result<()> {
+        if let Some(&held) = self.subscribe_id_to_alias.get(&subscribe_id) {
+            if held != track_alias {
+                return Err(Error::ErrStreamError(
+                    ErrorCode::ProtocolViolation,
+                    format!(
+                        "subscribe_id {} already uses track alias {}",
+                        subscribe_id, held
+                    ),
+                ));
+            }
+        }
+        if let Some(&holder) = self.alias_to_subscribe_id.get(&track_alias) {
+            if holder != subscribe_id {
+                return Err(Error::ErrStreamError(
+                    ErrorCode::DuplicateTrackAlias,
+                    format!(
+                        "Track alias {} is already used by subscribe_id {}",
+                        track_alias, holder
+                    ),
+                ));
+            }
+            return Ok(());
+        }
+        self.alias_to_subscribe_id.insert(track_alias, subscribe_id);
+        self.subscribe_id_to_alias.insert(subscribe_id, track_alias);
+        Ok(())
+    }
+
+    /// Frees the alias held by |subscribe_id|, on UNSUBSCRIBE, SUBSCRIBE_DONE
+    /// or SUBSCRIBE_ERROR. Returns the freed alias, if any.
+    pub fn remove(&mut self, subscribe_id: u64) -> Option<u64> {
+        let track_alias = self.subscribe_id_to_alias.remove(&subscribe_id)?;
+        self.alias_to_subscribe_id.remove(&track_alias);
+        Some(track_alias)
+    }
+
+    pub fn contains(&self, track_alias: u64) -> bool {
+        self.alias_to_subscribe_id.contains_key(&track_alias)
+    }
+
+    pub fn subscribe_id(&self, track_alias: u64) -> Option<u64> {
+        self.alias_to_subscribe_id.get(&track_alias).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reuse_while_active() -> Result<()> {
+        let mut table = TrackAliasTable::new();
+        table.insert(4, 1)?;
+        assert!(table.contains(4));
+        assert_eq!(table.subscribe_id(4), Some(1));
+        // The same subscription may repeat its alias.
+        table.insert(4, 1)?;
+
+        assert!(matches!(
+            table.insert(4, 2),
+            Err(Error::ErrStreamError(ErrorCode::DuplicateTrackAlias, _))
+        ));
+        assert_eq!(table.subscribe_id(4), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reuse_after_done() -> Result<()> {
+        let mut table = TrackAliasTable::new();
+        table.insert(4, 1)?;
+        assert_eq!(table.remove(1), Some(4));
+        assert!(!table.contains(4));
+        assert_eq!(table.remove(1), None);
+
+        table.insert(4, 2)?;
+        assert_eq!(table.subscribe_id(4), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_second_alias_for_subscribe_id() -> Result<()> {
+        let mut table = TrackAliasTable::new();
+        table.insert(4, 1)?;
+        assert!(matches!(
+            table.insert(5, 1),
+            Err(Error::ErrStreamError(ErrorCode::ProtocolViolation, _))
+        ));
+        assert!(!table.contains(5));
+
+        // Removing the subscription frees the only alias it holds.
+        assert_eq!(table.remove(1), Some(4));
+        assert!(!table.contains(4));
+        table.insert(4, 2)?;
+        Ok(())
+    }
+}